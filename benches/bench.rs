@@ -15,7 +15,7 @@ fn bench(c: &mut Criterion) {
 
                 let mut reader = BitsReader::new(writer.view_bytes());
                 for _ in 0..N {
-                    reader.read(bits);
+                    reader.read(bits).unwrap();
                 }
             })
         });
@@ -0,0 +1,207 @@
+//! Command line tool for inspecting and converting CSER blobs.
+
+use anyhow::Context;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use cser::{deserialize_cb_with, dynamic::Schema, json, sections, Strictness, Writer};
+use std::{
+    io::{Read, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+#[derive(Parser)]
+#[command(name = "cser", about = "Inspect and convert CSER blobs")]
+struct Opt {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+enum Format {
+    #[default]
+    Hex,
+    Base64,
+    Raw,
+}
+
+#[derive(Args)]
+struct Source {
+    /// File to read, stdin if omitted or `-`
+    file: Option<PathBuf>,
+    /// Read the input from the command line instead
+    #[arg(long, short, conflicts_with = "file")]
+    data: Option<String>,
+}
+
+impl Source {
+    fn read(&self) -> anyhow::Result<Vec<u8>> {
+        if let Some(data) = &self.data {
+            return Ok(data.as_bytes().to_vec());
+        }
+
+        let mut buf = vec![];
+        match &self.file {
+            Some(path) if path.as_os_str() != "-" => {
+                buf = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?
+            }
+            _ => {
+                std::io::stdin().read_to_end(&mut buf)?;
+            }
+        }
+        Ok(buf)
+    }
+}
+
+#[derive(Args)]
+struct Blob {
+    #[command(flatten)]
+    source: Source,
+    /// Encoding of the input
+    #[arg(long, short, value_enum, default_value_t)]
+    format: Format,
+}
+
+impl Blob {
+    fn read(&self) -> anyhow::Result<Vec<u8>> {
+        let buf = self.source.read()?;
+        Ok(match self.format {
+            Format::Raw => buf,
+            Format::Hex => {
+                let s = String::from_utf8(buf)?;
+                let s = s.trim();
+                hex::decode(s.strip_prefix("0x").unwrap_or(s))?
+            }
+            Format::Base64 => BASE64.decode(String::from_utf8(buf)?.trim())?,
+        })
+    }
+}
+
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct SchemaArg {
+    /// Type expression, e.g. `(u32, vec<option<u64>>, [u8; 32])`
+    #[arg(long, short)]
+    r#type: Option<String>,
+    /// File with struct and enum definitions followed by the type of the blob
+    #[arg(long, short)]
+    schema: Option<PathBuf>,
+}
+
+impl SchemaArg {
+    fn load(&self) -> anyhow::Result<Schema> {
+        match (&self.r#type, &self.schema) {
+            (Some(ty), _) => ty.parse(),
+            (_, Some(path)) => std::fs::read_to_string(path)
+                .with_context(|| format!("reading {}", path.display()))?
+                .parse()
+                .with_context(|| format!("parsing {}", path.display())),
+            (None, None) => unreachable!("required by clap"),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show the bytes section, bits section and footer
+    Split(Blob),
+    /// Dump the bits section with bit offsets, least significant bit first
+    Bits(Blob),
+    /// Decode against a schema and print JSON
+    Decode {
+        #[command(flatten)]
+        schema: SchemaArg,
+        /// Accept non-canonical encodings, reporting them on stderr
+        #[arg(long)]
+        lenient: bool,
+        #[command(flatten)]
+        blob: Blob,
+    },
+    /// Encode JSON against a schema
+    Encode {
+        #[command(flatten)]
+        schema: SchemaArg,
+        /// Encoding of the output
+        #[arg(long, short, value_enum, default_value_t)]
+        format: Format,
+        #[command(flatten)]
+        json: Source,
+    },
+    /// List every canonicality violation, exits with failure if there are any
+    Check {
+        #[command(flatten)]
+        schema: SchemaArg,
+        #[command(flatten)]
+        blob: Blob,
+    },
+}
+
+fn main() -> anyhow::Result<ExitCode> {
+    match Opt::parse().command {
+        Command::Split(blob) => {
+            let buf = blob.read()?;
+            let s = sections(&buf)?;
+            for (name, section) in [("bytes", s.bytes), ("bits", s.bits), ("footer", s.footer)] {
+                println!("{name:<6} ({:>4}): {}", section.len(), hex::encode(section));
+            }
+        }
+        Command::Bits(blob) => {
+            let buf = blob.read()?;
+            for (i, byte) in sections(&buf)?.bits.iter().enumerate() {
+                println!("{:>6}  {:08b}", i * 8, byte.reverse_bits());
+            }
+        }
+        Command::Decode {
+            schema,
+            lenient,
+            blob,
+        } => {
+            let schema = schema.load()?;
+            let strictness = if lenient {
+                Strictness::Lenient
+            } else {
+                Strictness::Strict
+            };
+            let (v, violations) =
+                deserialize_cb_with(&blob.read()?, strictness, |r| schema.decode(r))?;
+            for violation in violations {
+                eprintln!("warning: {violation}");
+            }
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&json::value_to_json(&v))?
+            );
+        }
+        Command::Encode {
+            schema,
+            format,
+            json,
+        } => {
+            let schema = schema.load()?;
+            let v = serde_json::from_slice(&json.read()?)?;
+            let mut w = Writer::new();
+            schema.encode(&json::value_from_json(&schema, &schema.root, &v)?, &mut w)?;
+            let out = w.output();
+            match format {
+                Format::Hex => println!("{}", hex::encode(out)),
+                Format::Base64 => println!("{}", BASE64.encode(out)),
+                Format::Raw => std::io::stdout().write_all(&out)?,
+            }
+        }
+        Command::Check { schema, blob } => {
+            let schema = schema.load()?;
+            let (_, violations) =
+                deserialize_cb_with(&blob.read()?, Strictness::Lenient, |r| schema.decode(r))?;
+            if violations.is_empty() {
+                println!("canonical");
+            } else {
+                for violation in &violations {
+                    println!("{violation}");
+                }
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,226 @@
+use alloc::vec::Vec;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{punctuated::Punctuated, Token};
+
+/// Options of `#[cser(...)]` on the struct or enum.
+#[derive(Default)]
+pub struct ContainerAttrs {
+    /// Version written ahead of the fields.
+    pub version: Option<u64>,
+    /// `fn(&mut Self, u64) -> anyhow::Result<()>` upgrading values decoded from an older
+    /// version.
+    pub migrate: Option<syn::Path>,
+    /// Where predicates replacing the inferred bounds of the `Encodable` impl.
+    pub encode_bound: Option<Vec<syn::WherePredicate>>,
+    /// Same for `Decodable`.
+    pub decode_bound: Option<Vec<syn::WherePredicate>>,
+    /// Same for `CserSchema`.
+    pub schema_bound: Option<Vec<syn::WherePredicate>>,
+    /// Whether the struct is encoded exactly like its only field.
+    pub transparent: bool,
+    /// Whether a `transparent` struct also gets `From` conversions to and from its field.
+    pub from: bool,
+    /// Width of the discriminant of a C-like enum, which is all it is encoded as.
+    pub bits: Option<usize>,
+}
+
+/// Options of `#[cser(...)]` on a field.
+#[derive(Default)]
+pub struct FieldAttrs {
+    /// First version with the field.
+    pub since: Option<u64>,
+    /// First version without the field.
+    pub until: Option<u64>,
+    /// Largest length accepted when decoding or encoding the field.
+    pub max_len: Option<syn::Expr>,
+    /// Width of the field stored directly in the bits section.
+    pub bits: Option<usize>,
+}
+
+fn int(meta: &syn::meta::ParseNestedMeta) -> syn::Result<u64> {
+    meta.value()?.parse::<syn::LitInt>()?.base10_parse()
+}
+
+fn bits(meta: &syn::meta::ParseNestedMeta) -> syn::Result<usize> {
+    let lit = meta.value()?.parse::<syn::LitInt>()?;
+    match lit.base10_parse()? {
+        bits @ 1..=64 => Ok(bits),
+        _ => Err(syn::Error::new(
+            lit.span(),
+            "`bits` must be between 1 and 64",
+        )),
+    }
+}
+
+fn predicates(meta: &syn::meta::ParseNestedMeta) -> syn::Result<Vec<syn::WherePredicate>> {
+    let predicates = meta
+        .value()?
+        .parse::<syn::LitStr>()?
+        .parse_with(Punctuated::<syn::WherePredicate, Token![,]>::parse_terminated)?;
+    Ok(predicates.into_iter().collect())
+}
+
+pub fn container_attrs(ast: &syn::DeriveInput) -> syn::Result<ContainerAttrs> {
+    let mut attrs = ContainerAttrs::default();
+    for attr in ast.attrs.iter().filter(|attr| attr.path().is_ident("cser")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("version") {
+                attrs.version = Some(int(&meta)?);
+            } else if meta.path.is_ident("migrate") {
+                attrs.migrate = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("transparent") {
+                attrs.transparent = true;
+            } else if meta.path.is_ident("from") {
+                attrs.from = true;
+            } else if meta.path.is_ident("bits") {
+                attrs.bits = Some(bits(&meta)?);
+            } else if meta.path.is_ident("bound") {
+                if meta.input.peek(Token![=]) {
+                    let bound = predicates(&meta)?;
+                    attrs.encode_bound = Some(bound.clone());
+                    attrs.decode_bound = Some(bound.clone());
+                    attrs.schema_bound = Some(bound);
+                } else {
+                    meta.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("encode") {
+                            attrs.encode_bound = Some(predicates(&meta)?);
+                        } else if meta.path.is_ident("decode") {
+                            attrs.decode_bound = Some(predicates(&meta)?);
+                        } else if meta.path.is_ident("schema") {
+                            attrs.schema_bound = Some(predicates(&meta)?);
+                        } else {
+                            return Err(meta.error("expected `encode`, `decode` or `schema`"));
+                        }
+                        Ok(())
+                    })?;
+                }
+            } else {
+                return Err(meta.error("unknown cser attribute"));
+            }
+            Ok(())
+        })?;
+    }
+
+    if attrs.bits.is_some() {
+        if !matches!(ast.data, syn::Data::Enum(_)) {
+            return Err(syn::Error::new_spanned(
+                &ast.ident,
+                "`bits` applies to C-like enums and to fields",
+            ));
+        }
+        if attrs.version.is_some() || attrs.transparent {
+            return Err(syn::Error::new_spanned(
+                &ast.ident,
+                "`bits` enums cannot be `version`ed or `transparent`",
+            ));
+        }
+    }
+    if attrs.transparent && attrs.version.is_some() {
+        return Err(syn::Error::new_spanned(
+            &ast.ident,
+            "`transparent` cannot be combined with `version`",
+        ));
+    }
+    if attrs.from && !attrs.transparent {
+        return Err(syn::Error::new_spanned(
+            &ast.ident,
+            "`from` requires `#[cser(transparent)]`",
+        ));
+    }
+    if attrs.migrate.is_some() && attrs.version.is_none() {
+        return Err(syn::Error::new_spanned(
+            &ast.ident,
+            "`migrate` requires `#[cser(version = ...)]`",
+        ));
+    }
+
+    Ok(attrs)
+}
+
+pub fn field_attrs(field: &syn::Field, container: &ContainerAttrs) -> syn::Result<FieldAttrs> {
+    let mut attrs = FieldAttrs::default();
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("cser"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("since") {
+                attrs.since = Some(int(&meta)?);
+            } else if meta.path.is_ident("until") {
+                attrs.until = Some(int(&meta)?);
+            } else if meta.path.is_ident("max_len") {
+                attrs.max_len = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("bits") {
+                attrs.bits = Some(bits(&meta)?);
+            } else {
+                return Err(meta.error("unknown cser attribute"));
+            }
+            Ok(())
+        })?;
+    }
+
+    if attrs.bits.is_some() && attrs.max_len.is_some() {
+        return Err(syn::Error::new_spanned(
+            field,
+            "`bits` cannot be combined with `max_len`",
+        ));
+    }
+    if container.transparent
+        && (attrs.is_versioned() || attrs.max_len.is_some() || attrs.bits.is_some())
+    {
+        return Err(syn::Error::new_spanned(
+            field,
+            "fields of `transparent` structs take no attributes",
+        ));
+    }
+    if attrs.since.is_some() || attrs.until.is_some() {
+        let Some(version) = container.version else {
+            return Err(syn::Error::new_spanned(
+                field,
+                "`since` and `until` require `#[cser(version = ...)]` on the struct",
+            ));
+        };
+        if attrs.since.is_some_and(|since| since > version) {
+            return Err(syn::Error::new_spanned(
+                field,
+                "`since` is newer than the struct version",
+            ));
+        }
+        if let (Some(since), Some(until)) = (attrs.since, attrs.until) {
+            if until <= since {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "`until` must be greater than `since`",
+                ));
+            }
+        }
+    }
+
+    Ok(attrs)
+}
+
+impl FieldAttrs {
+    /// Whether the field is written in `version`.
+    pub fn present_in(&self, version: u64) -> bool {
+        self.since.is_none_or(|since| since <= version)
+            && self.until.is_none_or(|until| version < until)
+    }
+
+    /// Whether the field depends on the version at all.
+    pub fn is_versioned(&self) -> bool {
+        self.since.is_some() || self.until.is_some()
+    }
+
+    /// Condition on the decoded `version` for the field to be present.
+    pub fn condition(&self) -> TokenStream {
+        let since = self.since.map(|since| quote! { #since <= version });
+        let until = self.until.map(|until| quote! { version < #until });
+        match (since, until) {
+            (Some(since), Some(until)) => quote! { #since && #until },
+            (Some(cond), None) | (None, Some(cond)) => cond,
+            (None, None) => quote! { true },
+        }
+    }
+}
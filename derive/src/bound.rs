@@ -0,0 +1,106 @@
+use alloc::vec::Vec;
+use syn::{
+    parse_quote,
+    visit::{self, Visit},
+};
+
+/// Collects the type parameters a type refers to, except within `PhantomData`, which
+/// encodes to nothing.
+struct TypeParams<'a> {
+    params: Vec<&'a syn::Ident>,
+    used: Vec<&'a syn::Ident>,
+}
+
+impl<'ast> Visit<'ast> for TypeParams<'_> {
+    fn visit_type_path(&mut self, ty: &'ast syn::TypePath) {
+        if ty.path.segments.last().unwrap().ident == "PhantomData" {
+            return;
+        }
+        if ty.qself.is_none() && ty.path.leading_colon.is_none() {
+            // `T` as well as `T::Assoc`
+            let first = &ty.path.segments.first().unwrap().ident;
+            if let Some(param) = self.params.iter().find(|param| **param == first) {
+                if !self.used.contains(param) {
+                    self.used.push(param);
+                }
+            }
+        }
+        visit::visit_type_path(self, ty)
+    }
+}
+
+/// Whether `ty` refers to a type parameter of `generics`.
+pub fn has_type_params(generics: &syn::Generics, ty: &syn::Type) -> bool {
+    let mut visitor = TypeParams {
+        params: generics.type_params().map(|param| &param.ident).collect(),
+        used: Vec::new(),
+    };
+    visitor.visit_type(ty);
+    !visitor.used.is_empty()
+}
+
+/// Generics of a derived impl: the type's own, plus either the `bound` predicates for every
+/// type parameter the fields refer to, or the `#[cser(bound = ...)]` predicates replacing
+/// them. Parameters of `bits` fields, flagged in `fields`, are bound on `BitPacked` instead.
+pub fn with_bounds<'a>(
+    generics: &syn::Generics,
+    fields: impl IntoIterator<Item = (&'a syn::Type, bool)>,
+    explicit: Option<&[syn::WherePredicate]>,
+    bound: impl Fn(&syn::Ident) -> Vec<syn::WherePredicate>,
+) -> syn::Generics {
+    let predicates: Vec<_> = match explicit {
+        Some(explicit) => explicit.to_vec(),
+        None => {
+            let params: Vec<_> = generics.type_params().map(|param| &param.ident).collect();
+            let mut encoded = TypeParams {
+                params: params.clone(),
+                used: Vec::new(),
+            };
+            let mut packed = TypeParams {
+                params,
+                used: Vec::new(),
+            };
+            for (ty, bits) in fields {
+                if bits {
+                    packed.visit_type(ty);
+                } else {
+                    encoded.visit_type(ty);
+                }
+            }
+            let packed = packed
+                .used
+                .into_iter()
+                .map(|param| parse_quote! { #param: cser::BitPacked });
+            encoded
+                .used
+                .into_iter()
+                .flat_map(bound)
+                .chain(packed)
+                .collect()
+        }
+    };
+    let mut generics = generics.clone();
+    generics.make_where_clause().predicates.extend(predicates);
+    generics
+}
+
+/// Generics of an impl forwarding to the only field, which requires of the field type
+/// exactly what it forwards to, unless `#[cser(bound = ...)]` says otherwise.
+pub fn wrapper_bounds(
+    ast: &syn::DeriveInput,
+    explicit: Option<&[syn::WherePredicate]>,
+    bound: syn::WherePredicate,
+) -> syn::Generics {
+    let mut generics = ast.generics.clone();
+    match explicit {
+        Some(explicit) => generics
+            .make_where_clause()
+            .predicates
+            .extend(explicit.iter().cloned()),
+        None if generics.type_params().next().is_some() => {
+            generics.make_where_clause().predicates.push(bound)
+        }
+        None => {}
+    }
+    generics
+}
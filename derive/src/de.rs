@@ -1,85 +1,223 @@
-use alloc::vec::Vec;
+use crate::{
+    attr::{container_attrs, field_attrs},
+    bound::{with_bounds, wrapper_bounds},
+    field_ident, struct_data, unit_variants, wrapped_field,
+};
+use alloc::{vec, vec::Vec};
 use proc_macro2::TokenStream;
 use quote::quote;
+use syn::parse_quote;
 
-pub fn impl_decodable(ast: &syn::DeriveInput) -> TokenStream {
-    let body = if let syn::Data::Struct(s) = &ast.data {
-        s
-    } else {
-        panic!("#[derive(Decodable)] is only defined for structs.");
-    };
+pub fn impl_decodable(ast: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let attrs = container_attrs(ast)?;
+    if let Some(bits) = attrs.bits {
+        return bits_enum_decodable(ast, bits);
+    }
+    let body = struct_data(ast, "#[derive(Decodable)]")?;
+    if attrs.transparent {
+        field_attrs(wrapped_field(ast, "#[cser(transparent)]")?, &attrs)?;
+        return transparent_decodable(
+            ast,
+            "#[cser(transparent)]",
+            attrs.decode_bound.as_deref(),
+            true,
+        );
+    }
 
-    let stmts: Vec<_> = body
-        .fields
-        .iter()
-        .enumerate()
-        .map(|(index, field)| {
-            let id = if let Some(ident) = &field.ident {
-                quote! { #ident }
-            } else {
-                let index = syn::Index::from(index);
-                quote! { #index }
-            };
+    let mut stmts = Vec::new();
+    let mut skips = Vec::new();
+    let mut field_types = Vec::new();
+    for (index, field) in body.fields.iter().enumerate() {
+        let field_attrs = field_attrs(field, &attrs)?;
+        field_types.push((&field.ty, field_attrs.bits.is_some()));
+
+        let id = field_ident(index, field);
+        let ty = &field.ty;
+
+        let (decode, skip) = match (&field_attrs.max_len, field_attrs.bits) {
+            (_, Some(bits)) => {
+                let decode = quote! {
+                    <#ty as cser::BitPacked>::from_bits(input.read_bits(#bits)?)
+                        .ok_or(cser::Error::OverFlowError)?
+                };
+                (decode.clone(), quote! { #decode; })
+            }
+            (Some(max_len), None) => (
+                quote! { <#ty as cser::BoundedDecodable>::decode_bounded(input, #max_len)? },
+                quote! { <#ty as cser::BoundedDecodable>::skip_bounded(input, #max_len)?; },
+            ),
+            (None, None) => (
+                quote! { <#ty as cser::Decodable>::decode(input)? },
+                quote! { <#ty as cser::Decodable>::skip(input)?; },
+            ),
+        };
+
+        if field_attrs.is_versioned() {
+            let condition = field_attrs.condition();
+            stmts.push(quote! {
+                #id: if #condition {
+                    #decode
+                } else {
+                    ::core::default::Default::default()
+                },
+            });
+            skips.push(quote! {
+                if #condition {
+                    #skip
+                }
+            });
+        } else {
+            stmts.push(quote! { #id: #decode, });
+            skips.push(skip);
+        }
+    }
 
-            quote! { #id: cser::Decodable::decode(input)?, }
-        })
-        .collect();
     let name = &ast.ident;
-    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let generics = with_bounds(
+        &ast.generics,
+        field_types,
+        attrs.decode_bound.as_deref(),
+        |param| {
+            vec![
+                parse_quote! { #param: cser::Decodable },
+                parse_quote! {
+                    <#param as cser::Decodable>::Error: ::core::convert::From<cser::Error>
+                },
+                parse_quote! {
+                    anyhow::Error: ::core::convert::From<<#param as cser::Decodable>::Error>
+                },
+            ]
+        },
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let (read_version, migrate) = match attrs.version {
+        Some(version) => {
+            let read_version = quote! {
+                let version = input.read_compact()?;
+                if version > #version {
+                    anyhow::bail!("unsupported version {} of {}", version, stringify!(#name));
+                }
+            };
+            let migrate = attrs.migrate.as_ref().map(|migrate| {
+                quote! {
+                    if version < #version {
+                        #migrate(&mut this, version)?;
+                    }
+                }
+            });
+            (Some(read_version), migrate)
+        }
+        None => (None, None),
+    };
+    let mutability = migrate.as_ref().map(|_| quote! { mut });
 
     let impl_block = quote! {
         impl #impl_generics cser::Decodable for #name #ty_generics #where_clause {
             type Error = anyhow::Error;
 
             fn decode(mut input: &mut cser::Reader) -> Result<Self, anyhow::Error> {
-                let this = Self {
+                #read_version
+                let #mutability this = Self {
                     #(#stmts)*
                 };
+                #migrate
                 ::core::result::Result::Ok(this)
             }
+
+            fn skip(input: &mut cser::Reader) -> Result<(), anyhow::Error> {
+                #read_version
+                #(#skips)*
+                ::core::result::Result::Ok(())
+            }
         }
     };
 
-    quote! {
+    Ok(quote! {
         const _: () = {
             extern crate cser;
             #impl_block
         };
-    }
+    })
 }
 
-pub fn impl_decodable_wrapper(ast: &syn::DeriveInput) -> TokenStream {
-    let body = if let syn::Data::Struct(s) = &ast.data {
-        s
-    } else {
-        panic!("#[derive(DecodableWrapper)] is only defined for structs.");
-    };
+/// Reads the discriminant written by `bits_enum_encodable`, rejecting unknown ones.
+fn bits_enum_decodable(ast: &syn::DeriveInput, bits: usize) -> syn::Result<TokenStream> {
+    let variants = unit_variants(ast)?;
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
 
-    assert_eq!(
-        body.fields.iter().count(),
-        1,
-        "#[derive(DecodableWrapper)] is only defined for structs with one field."
-    );
+    Ok(quote! {
+        const _: () = {
+            extern crate cser;
+            impl #impl_generics cser::Decodable for #name #ty_generics #where_clause {
+                type Error = cser::Error;
+
+                fn decode(input: &mut cser::Reader) -> Result<Self, cser::Error> {
+                    let v = input.read_bits(#bits)?;
+                    #(
+                        if v == Self::#variants as u64 {
+                            return ::core::result::Result::Ok(Self::#variants);
+                        }
+                    )*
+                    ::core::result::Result::Err(cser::Error::OverFlowError)
+                }
+            }
+        };
+    })
+}
 
-    let wrapped_ty = &body.fields.iter().next().unwrap().ty;
+pub fn impl_decodable_wrapper(ast: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    transparent_decodable(ast, "#[derive(DecodableWrapper)]", None, false)
+}
+
+/// `Decodable` counterpart of `transparent_encodable`.
+fn transparent_decodable(
+    ast: &syn::DeriveInput,
+    what: &str,
+    bound: Option<&[syn::WherePredicate]>,
+    forward_is_byte: bool,
+) -> syn::Result<TokenStream> {
+    let field = wrapped_field(ast, what)?;
+    let ident = field_ident(0, field);
+    let wrapped_ty = &field.ty;
 
     let name = &ast.ident;
-    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let generics = wrapper_bounds(ast, bound, parse_quote! { #wrapped_ty: cser::Decodable });
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let from_byte = forward_is_byte.then(|| {
+        quote! {
+            const FROM_BYTE: ::core::option::Option<fn(u8) -> Self> =
+                match <#wrapped_ty as cser::Decodable>::FROM_BYTE {
+                    ::core::option::Option::Some(_) => ::core::option::Option::Some(|b| Self {
+                        #ident: <#wrapped_ty as cser::Decodable>::FROM_BYTE.unwrap()(b),
+                    }),
+                    ::core::option::Option::None => ::core::option::Option::None,
+                };
+        }
+    });
 
     let impl_block = quote! {
         impl #impl_generics cser::Decodable for #name #ty_generics #where_clause {
             type Error = <#wrapped_ty as cser::Decodable>::Error;
 
+            #from_byte
+
             fn decode(buf: &mut cser::Reader<'_>) -> Result<Self, Self::Error> {
-                <#wrapped_ty as cser::Decodable>::decode(buf).map(Self)
+                <#wrapped_ty as cser::Decodable>::decode(buf).map(|v| Self { #ident: v })
+            }
+
+            fn skip(buf: &mut cser::Reader<'_>) -> Result<(), Self::Error> {
+                <#wrapped_ty as cser::Decodable>::skip(buf)
             }
         }
     };
 
-    quote! {
+    Ok(quote! {
         const _: () = {
             extern crate cser;
             #impl_block
         };
-    }
+    })
 }
@@ -1,28 +1,76 @@
-use alloc::vec::Vec;
+use crate::{
+    attr::{container_attrs, field_attrs},
+    bound::{has_type_params, with_bounds, wrapper_bounds},
+    field_ident, struct_data, unit_variants, wrapped_field,
+};
+use alloc::{vec, vec::Vec};
 use proc_macro2::TokenStream;
 use quote::quote;
+use syn::parse_quote;
 
-pub fn impl_encodable(ast: &syn::DeriveInput) -> TokenStream {
-    let body = if let syn::Data::Struct(s) = &ast.data {
-        s
-    } else {
-        panic!("#[derive(Encodable)] is only defined for structs.");
-    };
+pub fn impl_encodable(ast: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let attrs = container_attrs(ast)?;
+    if let Some(bits) = attrs.bits {
+        return bits_enum_encodable(ast, bits);
+    }
+    let body = struct_data(ast, "#[derive(Encodable)]")?;
+    if attrs.transparent {
+        let field = wrapped_field(ast, "#[cser(transparent)]")?;
+        field_attrs(field, &attrs)?;
+        let encodable = transparent_encodable(
+            ast,
+            "#[cser(transparent)]",
+            attrs.encode_bound.as_deref(),
+            true,
+        )?;
+        let conversions = attrs.from.then(|| transparent_conversions(ast, field));
+        return Ok(quote! { #encodable #conversions });
+    }
 
-    let stmts: Vec<_> = body
-        .fields
-        .iter()
-        .enumerate()
-        .map(|(index, field)| {
-            let ident = field_ident(index, field);
+    let name = &ast.ident;
+    let mut stmts = Vec::new();
+    if let Some(version) = attrs.version {
+        stmts.push(quote! { out.write_compact(#version); });
+    }
+    let mut field_types = Vec::new();
+    for (index, field) in body.fields.iter().enumerate() {
+        let field_attrs = field_attrs(field, &attrs)?;
+        field_types.push((&field.ty, field_attrs.bits.is_some()));
+        // fields dropped from the current version are not written
+        if attrs
+            .version
+            .is_some_and(|version| !field_attrs.present_in(version))
+        {
+            continue;
+        }
 
-            let id = quote! { self.#ident };
+        let ident = field_ident(index, field);
 
-            quote! { cser::Encodable::encode(&#id, out); }
-        })
-        .collect();
-    let name = &ast.ident;
-    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+        let id = quote! { self.#ident };
+
+        if let Some(max_len) = &field_attrs.max_len {
+            stmts.push(quote! {
+                assert!(
+                    #id.len() <= #max_len,
+                    "`{}` is longer than {}",
+                    concat!(stringify!(#name), "::", stringify!(#ident)),
+                    #max_len,
+                );
+            });
+        }
+        if let Some(bits) = field_attrs.bits {
+            stmts.push(quote! { out.write_bits(#bits, cser::BitPacked::to_bits(&#id)); });
+        } else {
+            stmts.push(quote! { cser::Encodable::encode(&#id, out); });
+        }
+    }
+    let generics = with_bounds(
+        &ast.generics,
+        field_types,
+        attrs.encode_bound.as_deref(),
+        |param| vec![parse_quote! { #param: cser::Encodable }],
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
     let impl_block = quote! {
         impl #impl_generics cser::Encodable for #name #ty_generics #where_clause {
@@ -32,55 +80,130 @@ pub fn impl_encodable(ast: &syn::DeriveInput) -> TokenStream {
         }
     };
 
-    quote! {
+    Ok(quote! {
         const _: () = {
             extern crate cser;
             #impl_block
         };
-    }
+    })
 }
 
-pub fn impl_encodable_wrapper(ast: &syn::DeriveInput) -> TokenStream {
-    let body = if let syn::Data::Struct(s) = &ast.data {
-        s
-    } else {
-        panic!("#[derive(EncodableWrapper)] is only defined for structs.");
-    };
+/// Writes the discriminant of a C-like enum into `bits` bits.
+fn bits_enum_encodable(ast: &syn::DeriveInput, bits: usize) -> syn::Result<TokenStream> {
+    let variants = unit_variants(ast)?;
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
 
-    let ident = {
-        let fields: Vec<_> = body.fields.iter().collect();
-        if fields.len() == 1 {
-            let field = fields.first().expect("fields.len() == 1; qed");
-            field_ident(0, field)
-        } else {
-            panic!("#[derive(EncodableWrapper)] is only defined for structs with one field.")
+    // discriminants are known up front, so ones that do not fit fail the build rather
+    // than the encoding
+    let checks = (bits < 64 && ast.generics.params.is_empty()).then(|| {
+        let width = proc_macro2::Literal::usize_unsuffixed(bits);
+        quote! {
+            #(
+                const _: () = ::core::assert!(
+                    (#name::#variants as u64) >> #width == 0,
+                    ::core::concat!(
+                        "discriminant of `",
+                        ::core::stringify!(#variants),
+                        "` does not fit into ",
+                        ::core::stringify!(#width),
+                        " bits",
+                    ),
+                );
+            )*
         }
-    };
+    });
+
+    Ok(quote! {
+        const _: () = {
+            extern crate cser;
+            #checks
+            impl #impl_generics cser::Encodable for #name #ty_generics #where_clause {
+                fn encode(&self, out: &mut cser::Writer) {
+                    let v = match self {
+                        #(Self::#variants => Self::#variants as u64,)*
+                    };
+                    out.write_bits(#bits, v);
+                }
+            }
+        };
+    })
+}
+
+pub fn impl_encodable_wrapper(ast: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    transparent_encodable(ast, "#[derive(EncodableWrapper)]", None, false)
+}
+
+/// `Encodable` forwarding to the only field. Transparent structs also take the layout of
+/// the field in sequences, wrappers keep the item by item one.
+fn transparent_encodable(
+    ast: &syn::DeriveInput,
+    what: &str,
+    bound: Option<&[syn::WherePredicate]>,
+    forward_is_byte: bool,
+) -> syn::Result<TokenStream> {
+    let field = wrapped_field(ast, what)?;
+    let ident = field_ident(0, field);
+    let wrapped_ty = &field.ty;
 
     let name = &ast.ident;
-    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let generics = wrapper_bounds(ast, bound, parse_quote! { #wrapped_ty: cser::Encodable });
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let is_byte = forward_is_byte.then(|| {
+        quote! { const IS_BYTE: bool = <#wrapped_ty as cser::Encodable>::IS_BYTE; }
+    });
 
     let impl_block = quote! {
         impl #impl_generics cser::Encodable for #name #ty_generics #where_clause {
+            #is_byte
+
             fn encode(&self, out: &mut cser::Writer) {
                 cser::Encodable::encode(&self.#ident, out)
             }
         }
     };
 
-    quote! {
+    Ok(quote! {
         const _: () = {
             extern crate cser;
             #impl_block
         };
-    }
+    })
 }
 
-fn field_ident(index: usize, field: &syn::Field) -> TokenStream {
-    if let Some(ident) = &field.ident {
-        quote! { #ident }
-    } else {
-        let index = syn::Index::from(index);
-        quote! { #index }
+/// `From` both ways between a transparent struct and its field. The conversion into the
+/// field is left out where the field type refers to a type parameter, which the orphan
+/// rules forbid for types like `T` or `Box<T>`.
+fn transparent_conversions(ast: &syn::DeriveInput, field: &syn::Field) -> TokenStream {
+    let ident = field_ident(0, field);
+    let wrapped_ty = &field.ty;
+
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let generic = has_type_params(&ast.generics, wrapped_ty);
+    let into_field = (!generic).then(|| {
+        quote! {
+            impl #impl_generics ::core::convert::From<#name #ty_generics>
+                for #wrapped_ty #where_clause
+            {
+                fn from(v: #name #ty_generics) -> Self {
+                    v.#ident
+                }
+            }
+        }
+    });
+
+    quote! {
+        impl #impl_generics ::core::convert::From<#wrapped_ty>
+            for #name #ty_generics #where_clause
+        {
+            fn from(v: #wrapped_ty) -> Self {
+                Self { #ident: v }
+            }
+        }
+
+        #into_field
     }
 }
@@ -5,37 +5,128 @@
 extern crate alloc;
 extern crate proc_macro;
 
+mod attr;
+mod bound;
 mod de;
 mod en;
+mod schema;
 
+use alloc::vec::Vec;
 use de::*;
 use en::*;
 use proc_macro::TokenStream;
+use schema::*;
+use syn::{parse_macro_input, spanned::Spanned};
 
-#[proc_macro_derive(Encodable)]
+/// Fields of the struct a derive is applied to.
+fn struct_data<'a>(ast: &'a syn::DeriveInput, what: &str) -> syn::Result<&'a syn::DataStruct> {
+    let span = match &ast.data {
+        syn::Data::Struct(s) => return Ok(s),
+        syn::Data::Enum(e) => e.enum_token.span,
+        syn::Data::Union(u) => u.union_token.span,
+    };
+    Err(syn::Error::new(
+        span,
+        format_args!("{what} is only defined for structs"),
+    ))
+}
+
+/// Variants of the C-like enum a `#[cser(bits = N)]` derive is applied to.
+fn unit_variants(ast: &syn::DeriveInput) -> syn::Result<Vec<&syn::Ident>> {
+    let syn::Data::Enum(data) = &ast.data else {
+        unreachable!("`bits` is only accepted on enums");
+    };
+    data.variants
+        .iter()
+        .map(|variant| match variant.fields {
+            syn::Fields::Unit => Ok(&variant.ident),
+            _ => Err(syn::Error::new_spanned(
+                &variant.fields,
+                "`bits` enums take unit variants only",
+            )),
+        })
+        .collect()
+}
+
+/// Expression suffix naming the field, `self.#ident`.
+fn field_ident(index: usize, field: &syn::Field) -> proc_macro2::TokenStream {
+    if let Some(ident) = &field.ident {
+        quote::quote! { #ident }
+    } else {
+        let index = syn::Index::from(index);
+        quote::quote! { #index }
+    }
+}
+
+/// The only field of the struct a wrapper derive is applied to.
+fn wrapped_field<'a>(ast: &'a syn::DeriveInput, what: &str) -> syn::Result<&'a syn::Field> {
+    let fields = &struct_data(ast, what)?.fields;
+    let mut iter = fields.iter();
+    match (iter.next(), iter.next()) {
+        (Some(field), None) => Ok(field),
+        (None, _) => Err(syn::Error::new(
+            ast.ident.span(),
+            format_args!("{what} is only defined for structs with one field"),
+        )),
+        (Some(_), Some(_)) => Err(syn::Error::new(
+            fields.span(),
+            format_args!("{what} is only defined for structs with one field"),
+        )),
+    }
+}
+
+/// Derives `Encodable`, writing the fields in order.
+///
+/// On `#[cser(transparent, from)]` structs it also derives `From` conversions between the
+/// struct and its field, both ways. The conversion into the field is left out when the
+/// field type refers to a type parameter.
+///
+/// # Panics
+///
+/// The derived `encode` panics if
+///
+/// - a field marked `#[cser(max_len = N)]` holds more than `N` items, naming the field in
+///   the message. Decoding rejects such values, so they are not written. To rule them out
+///   when the value is built instead, use `BoundedVec` or `BoundedBytes` as the field type.
+/// - an integer field marked `#[cser(bits = N)]` holds a value that does not fit into `N`
+///   bits, as `Writer::write_bits` does. Discriminants of `bits` enums are checked at
+///   compile time instead.
+#[proc_macro_derive(Encodable, attributes(cser))]
 pub fn encodable(input: TokenStream) -> TokenStream {
-    let ast = syn::parse(input).unwrap();
-    let gen = impl_encodable(&ast);
-    gen.into()
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+    impl_encodable(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
 
 #[proc_macro_derive(EncodableWrapper)]
 pub fn encodable_wrapper(input: TokenStream) -> TokenStream {
-    let ast = syn::parse(input).unwrap();
-    let gen = impl_encodable_wrapper(&ast);
-    gen.into()
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+    impl_encodable_wrapper(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
 
-#[proc_macro_derive(Decodable)]
+#[proc_macro_derive(Decodable, attributes(cser))]
 pub fn decodable(input: TokenStream) -> TokenStream {
-    let ast = syn::parse(input).unwrap();
-    let gen = impl_decodable(&ast);
-    gen.into()
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+    impl_decodable(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
 
 #[proc_macro_derive(DecodableWrapper)]
 pub fn decodable_wrapper(input: TokenStream) -> TokenStream {
-    let ast = syn::parse(input).unwrap();
-    let gen = impl_decodable_wrapper(&ast);
-    gen.into()
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+    impl_decodable_wrapper(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(CserSchema, attributes(cser))]
+pub fn cser_schema(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as syn::DeriveInput);
+    impl_cser_schema(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
@@ -0,0 +1,128 @@
+use crate::{
+    attr::{container_attrs, field_attrs},
+    bound::{with_bounds, wrapper_bounds},
+    struct_data, wrapped_field,
+};
+use alloc::{string::ToString, vec, vec::Vec};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{ext::IdentExt, parse_quote};
+
+pub fn impl_cser_schema(ast: &syn::DeriveInput) -> syn::Result<TokenStream> {
+    let body = struct_data(ast, "#[derive(CserSchema)]")?;
+    let attrs = container_attrs(ast)?;
+    if attrs.version.is_some() {
+        // the layout depends on the version prefix, which schemas do not describe
+        return Err(syn::Error::new_spanned(
+            &ast.ident,
+            "#[derive(CserSchema)] does not support versioned structs",
+        ));
+    }
+    if attrs.transparent {
+        let field = wrapped_field(ast, "#[cser(transparent)]")?;
+        field_attrs(field, &attrs)?;
+        return Ok(transparent_schema(
+            ast,
+            field,
+            attrs.schema_bound.as_deref(),
+        ));
+    }
+
+    for field in &body.fields {
+        if field_attrs(field, &attrs)?.bits.is_some() {
+            // the schema model has no types narrower than a byte
+            return Err(syn::Error::new_spanned(
+                field,
+                "#[derive(CserSchema)] does not support `bits` fields",
+            ));
+        }
+    }
+
+    let types: Vec<_> = body
+        .fields
+        .iter()
+        .map(|field| {
+            let ty = &field.ty;
+            quote! { <#ty as cser::CserSchema>::schema_type(definitions) }
+        })
+        .collect();
+    let fields = match &body.fields {
+        syn::Fields::Named(fields) => {
+            let names = fields
+                .named
+                .iter()
+                .map(|field| field.ident.as_ref().unwrap().unraw().to_string());
+            quote! {
+                cser::dynamic::Fields::Named([#((#names.into(), #types),)*].into())
+            }
+        }
+        syn::Fields::Unnamed(_) => quote! {
+            cser::dynamic::Fields::Tuple([#(#types,)*].into())
+        },
+        syn::Fields::Unit => quote! { cser::dynamic::Fields::Unit },
+    };
+
+    let name = &ast.ident;
+    let generics = with_bounds(
+        &ast.generics,
+        body.fields.iter().map(|field| (&field.ty, false)),
+        attrs.schema_bound.as_deref(),
+        |param| vec![parse_quote! { #param: cser::CserSchema }],
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let impl_block = quote! {
+        impl #impl_generics cser::CserSchema for #name #ty_generics #where_clause {
+            fn schema_type(
+                definitions: &mut cser::dynamic::Definitions,
+            ) -> cser::dynamic::Type {
+                // unique per type, `CserSchema::schema` turns it into a readable name
+                let name = ::core::any::type_name::<Self>();
+                if !definitions.contains_key(name) {
+                    // placeholder, so that recursive types refer back to this definition
+                    definitions.insert(
+                        name.into(),
+                        cser::dynamic::Definition::Struct(cser::dynamic::Fields::Unit),
+                    );
+                    let fields = #fields;
+                    definitions.insert(name.into(), cser::dynamic::Definition::Struct(fields));
+                }
+                cser::dynamic::Type::Named(name.into())
+            }
+        }
+    };
+
+    Ok(quote! {
+        const _: () = {
+            extern crate cser;
+            #impl_block
+        };
+    })
+}
+
+/// Schema of the only field, which transparent structs are encoded as.
+fn transparent_schema(
+    ast: &syn::DeriveInput,
+    field: &syn::Field,
+    bound: Option<&[syn::WherePredicate]>,
+) -> TokenStream {
+    let wrapped_ty = &field.ty;
+
+    let name = &ast.ident;
+    let generics = wrapper_bounds(ast, bound, parse_quote! { #wrapped_ty: cser::CserSchema });
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        const _: () = {
+            extern crate cser;
+
+            impl #impl_generics cser::CserSchema for #name #ty_generics #where_clause {
+                fn schema_type(
+                    definitions: &mut cser::dynamic::Definitions,
+                ) -> cser::dynamic::Type {
+                    <#wrapped_ty as cser::CserSchema>::schema_type(definitions)
+                }
+            }
+        };
+    }
+}
@@ -0,0 +1,10 @@
+use cser::Encodable;
+
+#[derive(Encodable)]
+#[cser(bits = 2)]
+enum Kind {
+    Event,
+    Block(u8),
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use cser::Decodable;
+
+#[derive(Decodable)]
+#[cser(bits = 2)]
+struct Header {
+    kind: u8,
+}
+
+fn main() {}
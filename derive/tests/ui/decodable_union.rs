@@ -0,0 +1,9 @@
+use cser::Decodable;
+
+#[derive(Decodable)]
+union Word {
+    int: u32,
+    bytes: [u8; 4],
+}
+
+fn main() {}
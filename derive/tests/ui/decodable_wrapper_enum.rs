@@ -0,0 +1,9 @@
+use cser::DecodableWrapper;
+
+#[derive(DecodableWrapper)]
+enum Id {
+    Short(u32),
+    Long(u64),
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use cser::DecodableWrapper;
+
+#[derive(DecodableWrapper)]
+struct Empty;
+
+fn main() {}
@@ -0,0 +1,9 @@
+use cser::Encodable;
+
+#[derive(Encodable)]
+enum Vote {
+    Skip,
+    Epoch(u32),
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use cser::EncodableWrapper;
+
+#[derive(EncodableWrapper)]
+struct Pair(u32, u64);
+
+fn main() {}
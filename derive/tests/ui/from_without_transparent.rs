@@ -0,0 +1,7 @@
+use cser::Encodable;
+
+#[derive(Encodable)]
+#[cser(from)]
+struct Epoch(u32);
+
+fn main() {}
@@ -0,0 +1,10 @@
+use cser::Encodable;
+
+// `From<Boxed<T>> for Box<T>` would break the orphan rules, only the other way is derived
+#[derive(Encodable)]
+#[cser(transparent, from)]
+struct Boxed<T>(Box<T>);
+
+fn main() {
+    let _ = Boxed::from(Box::new(7_u64));
+}
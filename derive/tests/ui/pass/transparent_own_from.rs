@@ -0,0 +1,16 @@
+use cser::{Decodable, Encodable};
+
+// without `from` the derive leaves the conversions to the struct
+#[derive(Encodable, Decodable)]
+#[cser(transparent)]
+struct Nonce(u64);
+
+impl From<u64> for Nonce {
+    fn from(v: u64) -> Self {
+        Self(v)
+    }
+}
+
+fn main() {
+    let _ = Nonce::from(7);
+}
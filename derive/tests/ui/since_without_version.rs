@@ -0,0 +1,10 @@
+use cser::{Decodable, Encodable};
+
+#[derive(Encodable, Decodable)]
+struct Header {
+    epoch: u32,
+    #[cser(since = 2)]
+    fork: u16,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use cser::{Decodable, Encodable};
+
+#[derive(Encodable, Decodable)]
+#[cser(transparent)]
+struct Header {
+    epoch: u32,
+    fork: u16,
+}
+
+fn main() {}
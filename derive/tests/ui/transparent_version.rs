@@ -0,0 +1,7 @@
+use cser::Encodable;
+
+#[derive(Encodable)]
+#[cser(transparent, version = 2)]
+struct Epoch(u32);
+
+fn main() {}
@@ -0,0 +1,9 @@
+use cser::{Decodable, Encodable};
+
+#[derive(Encodable, Decodable)]
+struct Header {
+    #[cser(skip)]
+    epoch: u32,
+}
+
+fn main() {}
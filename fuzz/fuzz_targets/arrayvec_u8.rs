@@ -0,0 +1,5 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| cser_fuzz::roundtrip::<arrayvec::ArrayVec<u8, 32>>(data));
@@ -0,0 +1,106 @@
+//! Shared harness for the fuzz targets.
+
+use bytes::Bytes;
+use cser::{
+    check_canonical, deserialize, deserialize_cb_with, deserialize_with, Decodable, DecodableWrapper, Encodable,
+    EncodableWrapper, Error, Strictness, Writer,
+};
+use ethereum_types::{Address, H256};
+use std::fmt::Debug;
+
+/// Decodes `data` as `T` and, if it is accepted, checks that encoding the value again
+/// reproduces `data` byte for byte: the decoder must only accept canonical encodings.
+/// Skipping must accept exactly the same inputs as decoding.
+pub fn roundtrip<T>(data: &[u8])
+where
+    T: Decodable + Encodable + Debug,
+    T::Error: From<Error>,
+{
+    let skipped = deserialize_cb_with(data, Strictness::Strict, T::skip).is_ok();
+    let decoded = deserialize_with::<T>(data, Strictness::Strict);
+    assert_eq!(decoded.is_ok(), skipped, "skip disagrees with decode");
+
+    if let Ok((v, _)) = decoded {
+        let mut w = Writer::new();
+        v.encode(&mut w);
+        assert_eq!(w.output(), data, "non-canonical encoding accepted: {v:?}");
+    }
+}
+
+/// Checks that lenient decoding reports violations exactly for the inputs that strict
+/// decoding rejects as non-canonical, and that the default mode sits in between.
+pub fn lenient<T>(data: &[u8])
+where
+    T: Decodable + Debug,
+    T::Error: From<Error> + Debug,
+{
+    let strict = deserialize_with::<T>(data, Strictness::Strict);
+    let compatible = deserialize::<T>(data);
+    match (&strict, check_canonical::<T>(data)) {
+        (Ok(_), Ok(violations)) => assert!(violations.is_empty(), "{violations:?}"),
+        (Err(_), Ok(violations)) => assert!(!violations.is_empty()),
+        (Ok(v), Err(e)) => panic!("{v:?} accepted only in strict mode: {e:?}"),
+        (Err(_), Err(_)) => {}
+    }
+    assert!(strict.is_err() || compatible.is_ok(), "{strict:?}");
+}
+
+#[derive(Debug, EncodableWrapper, DecodableWrapper)]
+pub struct EventId(H256);
+
+/// Event-like structure mixing bits-stream and bytes-stream fields.
+#[derive(Debug, Encodable, Decodable)]
+pub struct Event {
+    pub epoch: u32,
+    pub seq: u32,
+    pub frame: u32,
+    pub creator: Address,
+    pub lamport: u32,
+    pub creation_time: u64,
+    pub median_time: i64,
+    pub is_root: bool,
+    pub prev_epoch_hash: Option<H256>,
+    pub parents: Vec<EventId>,
+    pub gas_power_left: Vec<Option<u64>>,
+    pub extra: Bytes,
+}
+
+/// Decodes `data` as `T`, which accepts older versions of its encoding, and checks that
+/// once encoded at the current version the value decodes to itself. Fields dropped by
+/// later versions are lost on the way.
+pub fn reencode<T>(data: &[u8])
+where
+    T: Decodable + Encodable + Debug + PartialEq,
+    T::Error: From<Error> + Debug,
+{
+    let skipped = deserialize_cb_with(data, Strictness::Strict, T::skip).is_ok();
+    let decoded = deserialize_with::<T>(data, Strictness::Strict);
+    assert_eq!(decoded.is_ok(), skipped, "skip disagrees with decode");
+
+    if let Ok((v, _)) = decoded {
+        let mut w = Writer::new();
+        v.encode(&mut w);
+        let encoded = w.output();
+        let current = deserialize::<T>(&encoded).unwrap();
+        let mut w = Writer::new();
+        current.encode(&mut w);
+        assert_eq!(
+            w.output(),
+            encoded,
+            "{v:?} changed on re-encoding: {current:?}"
+        );
+    }
+}
+
+/// Versioned structure, with fields only present in some versions.
+#[derive(Debug, PartialEq, Encodable, Decodable)]
+#[cser(version = 3)]
+pub struct Versioned {
+    pub epoch: u32,
+    #[cser(until = 2)]
+    pub legacy_fork: u64,
+    #[cser(since = 2)]
+    pub fork: u16,
+    #[cser(since = 3)]
+    pub flags: Vec<bool>,
+}
@@ -1,32 +1,37 @@
 use super::{
-    bits,
-    error::Error,
+    error::{Error, Strictness, Violation},
     fast,
-    read_writer::{read_uint64_compact, write_uint64_compact, Reader},
+    read_writer::{read_uint64_compact_lenient, write_uint64_compact, Reader},
     Decodable,
 };
+use alloc::vec::Vec;
 
 /// Packs body bytes and bits into raw
 pub(crate) fn binary_from_cser(bbits: &[u8], bbytes: Vec<u8>) -> Vec<u8> {
     let mut body_bytes = fast::Writer::new(bbytes);
     body_bytes.write(bbits);
-    // write bits size
+    body_bytes.write(&bits_size_footer(bbits.len()));
+    body_bytes.buf
+}
+
+/// Bits size written in reverse, so that it can be read from the end of raw
+pub(crate) fn bits_size_footer(bits_size: usize) -> Vec<u8> {
     let mut size_writer = fast::Writer::new(Vec::with_capacity(4));
-    write_uint64_compact(&mut size_writer, bbits.len().try_into().unwrap());
+    write_uint64_compact(&mut size_writer, bits_size.try_into().unwrap());
 
     let mut size_buf = size_writer.buf;
     size_buf.reverse();
-    body_bytes.write(&size_buf);
-    body_bytes.buf
+    size_buf
 }
 
-/// Unpacks raw on body bytes and bits
-pub(crate) fn binary_to_cser(mut raw: &[u8]) -> Result<(&[u8], &[u8]), Error> {
+/// Unpacks raw on body bytes and bits, also telling whether the bits size is canonical
+pub(crate) fn binary_to_cser(mut raw: &[u8]) -> Result<(&[u8], &[u8], bool), Error> {
     // read bitsArray size
     let mut bits_size_buf = tail(raw, 9).to_vec();
     bits_size_buf.reverse();
     let mut bits_size_reader = fast::Reader::new(&bits_size_buf);
-    let bits_size = usize::try_from(read_uint64_compact(&mut bits_size_reader)?).unwrap();
+    let (bits_size, canonical) = read_uint64_compact_lenient(&mut bits_size_reader)?;
+    let bits_size = usize::try_from(bits_size).unwrap();
     raw = &raw[..raw.len() - bits_size_reader.position()];
 
     if raw.len() < bits_size {
@@ -35,7 +40,27 @@ pub(crate) fn binary_to_cser(mut raw: &[u8]) -> Result<(&[u8], &[u8]), Error> {
 
     let (bbytes, bbits) = raw.split_at(raw.len() - bits_size);
 
-    Ok((bbits, bbytes))
+    Ok((bbits, bbytes, canonical))
+}
+
+/// Sections of a raw encoding, in the order they appear in it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sections<'a> {
+    pub bytes: &'a [u8],
+    pub bits: &'a [u8],
+    /// Size of the bits section, written backwards.
+    pub footer: &'a [u8],
+}
+
+/// Splits raw encoding into its sections without decoding them.
+pub fn sections(raw: &[u8]) -> Result<Sections<'_>, Error> {
+    let (bits, bytes, _) = binary_to_cser(raw)?;
+
+    Ok(Sections {
+        bytes,
+        bits,
+        footer: &raw[bytes.len() + bits.len()..],
+    })
 }
 
 pub fn deserialize<T>(input: &[u8]) -> Result<T, T::Error>
@@ -46,6 +71,29 @@ where
     deserialize_cb::<T, T::Error>(input, |handler| T::decode(handler))
 }
 
+/// Deserializes `input` with the given strictness, returning the value together with
+/// the canonicality violations accepted on the way. There are none in strict mode.
+pub fn deserialize_with<T>(
+    input: &[u8],
+    strictness: Strictness,
+) -> Result<(T, Vec<Violation>), T::Error>
+where
+    T: Decodable,
+    T::Error: From<Error>,
+{
+    deserialize_cb_with::<T, T::Error>(input, strictness, |handler| T::decode(handler))
+}
+
+/// Decodes `input` leniently and returns every deviation from the canonical encoding.
+/// An empty list means `input` is exactly what encoding the decoded value produces.
+pub fn check_canonical<T>(input: &[u8]) -> Result<Vec<Violation>, T::Error>
+where
+    T: Decodable,
+    T::Error: From<Error>,
+{
+    deserialize_with::<T>(input, Strictness::Lenient).map(|(_, violations)| violations)
+}
+
 fn deserialize_cb<T, E>(
     input: &[u8],
     handler: impl FnOnce(&mut Reader) -> Result<T, E>,
@@ -53,27 +101,71 @@ fn deserialize_cb<T, E>(
 where
     E: From<Error>,
 {
-    let (bbits, bbytes) = binary_to_cser(input)?;
+    deserialize_cb_with(input, Strictness::default(), handler).map(|(out, _)| out)
+}
+
+/// Deserializes `input` with a custom `handler` reading the body, applying the same
+/// end-of-input checks as [`deserialize_with`].
+pub fn deserialize_cb_with<T, E>(
+    input: &[u8],
+    strictness: Strictness,
+    handler: impl FnOnce(&mut Reader) -> Result<T, E>,
+) -> Result<(T, Vec<Violation>), E>
+where
+    E: From<Error>,
+{
+    let (bbits, bbytes, canonical_footer) = binary_to_cser(input)?;
+
+    let mut body_reader = Reader::new(bbits, bbytes);
+    body_reader.strictness = strictness;
+    if !canonical_footer {
+        body_reader.non_canonical(Violation::FooterSize)?;
+    }
 
-    let mut body_reader = Reader {
-        bits_r: bits::Reader::new(bbits),
-        bytes_r: fast::Reader::new(bbytes),
-    };
     let out = (handler)(&mut body_reader)?;
+    check_end(&mut body_reader, false)?;
+
+    Ok((out, body_reader.take_violations()))
+}
+
+/// Checks that everything in `r` is read, only the padding of the last bits byte may be
+/// left. With `tolerate_trailing` all leftover data is tolerated rather than only what
+/// go-opera accepts, so that it fails in strict mode only.
+pub(crate) fn check_end(r: &mut Reader<'_>, tolerate_trailing: bool) -> Result<(), Error> {
+    let trailing = |r: &mut Reader<'_>, violation| {
+        if tolerate_trailing {
+            r.tolerated_non_canonical(violation)
+        } else {
+            r.non_canonical(violation)
+        }
+    };
 
-    // check that everything is read
-    if body_reader.bits_r.non_read_bytes() > 1 {
-        return Err(Error::NonCanonicalEncoding.into());
+    let unused_bits_bytes = r.bits_r.non_read_bits() / 8;
+    if unused_bits_bytes > 0 {
+        let violation = Violation::UnusedBitsBytes {
+            count: unused_bits_bytes,
+        };
+        // go-opera accepts one whole unused byte, as long as it is zero
+        if r.bits_r.non_read_bytes() > 1 {
+            trailing(r, violation)?;
+        } else {
+            r.tolerated_non_canonical(violation)?;
+        }
+    }
+    let mut tail = 0;
+    while r.bits_r.non_read_bits() > 0 {
+        tail |= r.bits_r.read(r.bits_r.non_read_bits().min(8))?;
     }
-    let tail = body_reader.bits_r.read(body_reader.bits_r.non_read_bits());
     if tail != 0 {
-        return Err(Error::NonCanonicalEncoding.into());
+        trailing(r, Violation::NonZeroPadding)?;
     }
-    if !body_reader.bytes_r.empty() {
-        return Err(Error::NonCanonicalEncoding.into());
+    if !r.bytes_r.empty() {
+        let violation = Violation::TrailingBytes {
+            count: r.bytes_r.remaining(),
+        };
+        trailing(r, violation)?;
     }
-
-    Ok(out)
+    Ok(())
 }
 
 fn tail(b: &[u8], cap: usize) -> &[u8] {
@@ -141,7 +233,7 @@ mod tests {
         // "Read 0"
 
         // unpack
-        let (_, bbytes) = binary_to_cser(&buf).unwrap();
+        let (_, bbytes, _) = binary_to_cser(&buf).unwrap();
         let l = bbytes.len();
         // pack with wrong bits size
         let mut corrupted = fast::Writer::new(bbytes.to_vec());
@@ -168,7 +260,7 @@ mod tests {
         let repack_with_defect =
             |defect: Box<dyn FnOnce(&mut Vec<u8>, &mut Vec<u8>) -> Result<(), Error>>| {
                 // unpack
-                let (bbits, bbytes) = binary_to_cser(&buf).unwrap();
+                let (bbits, bbytes, _) = binary_to_cser(&buf).unwrap();
                 let mut bbits = bbits.to_vec();
                 let mut bbytes = bbytes.to_vec();
                 // pack with defect
@@ -205,6 +297,137 @@ mod tests {
         }));
     }
 
+    #[test]
+    fn split_sections() {
+        let buf = [0xBB, 0xAA, 0xDD, 0xCC, 0x24, 0x81];
+        assert_eq!(
+            sections(&buf),
+            Ok(Sections {
+                bytes: &buf[..4],
+                bits: &buf[4..5],
+                footer: &buf[5..],
+            })
+        );
+        assert_eq!(sections(&[0x00, 0x85]), Err(Error::MalformedEncoding));
+    }
+
+    #[test]
+    fn malformed() {
+        // bits section is shorter than the size bits of u64
+        assert_eq!(deserialize::<u64>(&[0x80]), Err(Error::MalformedEncoding));
+        // count of Vec elements points past the end of the input
+        assert_eq!(
+            deserialize::<Vec<u64>>(&[0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x83]),
+            Err(Error::MalformedEncoding)
+        );
+    }
+
+    #[test]
+    fn non_canonical() {
+        // go-opera accepts these, so only strict mode rejects them
+        // unused zero byte in the bits section
+        let buf = [0x05, 0x00, 0x81];
+        assert_eq!(deserialize::<u8>(&[0x05, 0x80]), Ok(5));
+        assert_eq!(
+            deserialize_with::<u8>(&buf, Strictness::Compatible),
+            Ok((5, vec![Violation::UnusedBitsBytes { count: 1 }]))
+        );
+        assert_eq!(
+            deserialize_with::<u8>(&buf, Strictness::Strict),
+            Err(Error::NonCanonicalEncoding)
+        );
+        // zero U56 written with one byte
+        let buf = [0x00, 0x01, 0x81];
+        assert_eq!(deserialize::<U56>(&[0x00, 0x81]), Ok(U56(0)));
+        assert_eq!(
+            deserialize_with::<U56>(&buf, Strictness::Compatible),
+            Ok((U56(0), vec![Violation::IntegerZeroHighByte { offset: 0 }]))
+        );
+        assert_eq!(
+            deserialize_with::<U56>(&buf, Strictness::Strict),
+            Err(Error::NonCanonicalEncoding)
+        );
+        // U256 with a leading zero byte
+        let buf = [0x02, 0x00, 0x01, 0x01, 0x81];
+        assert_eq!(
+            deserialize::<U256>(&[0x01, 0x01, 0x01, 0x81]),
+            Ok(1.as_u256())
+        );
+        assert_eq!(
+            deserialize_with::<U256>(&buf, Strictness::Compatible),
+            Ok((
+                1.as_u256(),
+                vec![Violation::BigIntegerLeadingZero { offset: 0 }]
+            ))
+        );
+        assert_eq!(
+            deserialize_with::<U256>(&buf, Strictness::Strict),
+            Err(Error::NonCanonicalEncoding)
+        );
+
+        // go-opera rejects these as well
+        // two unused bytes in the bits section
+        assert_eq!(
+            deserialize::<u8>(&[0x05, 0x00, 0x00, 0x82]),
+            Err(Error::NonCanonicalEncoding)
+        );
+        // zero U56 written with two bytes
+        assert_eq!(
+            deserialize::<U56>(&[0x00, 0x00, 0x02, 0x81]),
+            Err(Error::NonCanonicalEncoding)
+        );
+    }
+
+    #[test]
+    fn lenient() {
+        // bytes: u16 with a zero high byte, negative zero i64, one trailing byte
+        // bits: sizes and sign, non-zero padding, one unused byte
+        // footer: bits size with a redundant zero group
+        let buf = [0x05, 0x00, 0x00, 0xFF, 0x83, 0x00, 0x80, 0x02];
+        let handler = |r: &mut Reader| Ok::<_, Error>((u16::decode(r)?, i64::decode(r)?));
+
+        assert_eq!(
+            deserialize_cb(&buf, handler),
+            Err(Error::NonCanonicalEncoding)
+        );
+        assert_eq!(
+            deserialize_cb_with(&buf, Strictness::Lenient, handler),
+            Ok((
+                (5, 0),
+                vec![
+                    Violation::FooterSize,
+                    Violation::IntegerZeroHighByte { offset: 0 },
+                    Violation::NegativeZero { offset: 2 },
+                    Violation::UnusedBitsBytes { count: 1 },
+                    Violation::NonZeroPadding,
+                    Violation::TrailingBytes { count: 1 },
+                ]
+            ))
+        );
+
+        let buf = [0x02, 0x00, 0x01, 0x01, 0x81];
+        assert_eq!(
+            deserialize_with::<U256>(&buf, Strictness::Lenient),
+            Ok((
+                1.as_u256(),
+                vec![Violation::BigIntegerLeadingZero { offset: 0 }]
+            ))
+        );
+        assert_eq!(
+            check_canonical::<U256>(&buf),
+            Ok(vec![Violation::BigIntegerLeadingZero { offset: 0 }])
+        );
+        assert_eq!(
+            check_canonical::<U256>(&[0x01, 0x01, 0x01, 0x81]),
+            Ok(vec![])
+        );
+        // malformed input is still an error
+        assert_eq!(
+            check_canonical::<U256>(&[0x02, 0x01, 0x81]),
+            Err(Error::MalformedEncoding)
+        );
+    }
+
     #[test]
     fn vals() {
         let exp_u256 = [0.as_u256(), 1.as_u256(), 0xF_FF_FF.as_u256(), U256::MAX];
@@ -1,3 +1,6 @@
+use super::error::Error;
+use alloc::vec::Vec;
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Writer {
     bytes: Vec<u8>,
@@ -64,6 +67,21 @@ impl Writer {
     pub fn view_bytes(&self) -> &[u8] {
         &self.bytes
     }
+
+    /// Bits written into the last byte, 0 if it is full.
+    pub(crate) fn bit_offset(&self) -> usize {
+        self.bit_offset
+    }
+
+    /// Drops everything written after `len` bytes with `bit_offset` bits in the last one.
+    pub(crate) fn truncate(&mut self, len: usize, bit_offset: usize) {
+        self.bytes.truncate(len);
+        self.bit_offset = bit_offset;
+        if bit_offset > 0 {
+            // writes are OR-ed into the last byte, clear the bits written after it
+            self.bytes[len - 1] &= (1 << bit_offset) - 1;
+        }
+    }
 }
 
 impl<'a> Reader<'a> {
@@ -79,7 +97,15 @@ impl<'a> Reader<'a> {
         8 - self.bit_offset
     }
 
-    pub fn read(&mut self, bits: usize) -> usize {
+    pub fn read(&mut self, bits: usize) -> Result<usize, Error> {
+        if bits > self.non_read_bits() {
+            return Err(Error::MalformedEncoding);
+        }
+
+        Ok(self.read_unchecked(bits))
+    }
+
+    fn read_unchecked(&mut self, bits: usize) -> usize {
         // perform all the checks in the same function to make CPU branch predictor work better
         if bits == 0 {
             return 0;
@@ -107,13 +133,29 @@ impl<'a> Reader<'a> {
             self.bit_offset = 0;
             self.byte_offset += 1;
             // read rest
-            let rest = self.read(bits - to_read);
+            let rest = self.read_unchecked(bits - to_read);
             v |= rest << to_read;
         }
         v
     }
 
-    pub fn view(&self, bits: usize) -> usize {
+    /// Number of bits consumed so far.
+    pub fn position(&self) -> usize {
+        self.byte_offset * 8 + self.bit_offset
+    }
+
+    /// Moves the cursor to `position` bits from the start.
+    pub(crate) fn seek(&mut self, position: usize) -> Result<(), Error> {
+        if position > self.bytes.len() * 8 {
+            return Err(Error::MalformedEncoding);
+        }
+
+        self.byte_offset = position / 8;
+        self.bit_offset = position % 8;
+        Ok(())
+    }
+
+    pub fn view(&self, bits: usize) -> Result<usize, Error> {
         self.clone().read(bits)
     }
 
@@ -207,11 +249,7 @@ mod tests {
     }
 
     fn bytes_to_fit(bits: usize) -> usize {
-        if bits % 8 == 0 {
-            bits / 8
-        } else {
-            bits / 8 + 1
-        }
+        bits.div_ceil(8)
     }
 
     #[derive(Clone, Copy)]
@@ -248,7 +286,7 @@ mod tests {
                 "{name}"
             );
 
-            let v = reader.read(w.bits);
+            let v = reader.read(w.bits).unwrap();
             assert_eq!(w.v, v, "{name}");
             total_bits_read += w.bits;
 
@@ -265,9 +303,10 @@ mod tests {
         }
 
         // read the tail
-        let zero = reader.read(reader.non_read_bits());
+        let zero = reader.read(reader.non_read_bits()).unwrap();
         assert_eq!(0, zero, "{name}");
         assert_eq!(0, reader.non_read_bits(), "{name}");
         assert_eq!(0, reader.non_read_bytes(), "{name}");
+        assert_eq!(Err(Error::MalformedEncoding), reader.read(1), "{name}");
     }
 }
@@ -0,0 +1,233 @@
+use super::{
+    dynamic::{Definitions, Type},
+    error::Error,
+    imp::{decode_seq_len, decode_vec, encode_seq, skip_seq},
+    CserSchema, Decodable, Encodable, Reader, Writer,
+};
+use alloc::{string::String, vec::Vec};
+use bytes::Bytes;
+use derive_more::Deref;
+
+/// Values whose length can be capped while decoding, before anything is allocated for it.
+/// Derived implementations use it for fields marked `#[cser(max_len = ...)]`.
+pub trait BoundedDecodable: Decodable {
+    /// Decodes a value of at most `max_len` items, or bytes for byte strings, failing with
+    /// [`Error::TooLargeAlloc`] on longer ones.
+    fn decode_bounded(buf: &mut Reader<'_>, max_len: usize) -> Result<Self, Self::Error>;
+
+    /// Advances past a value, accepting exactly what [`BoundedDecodable::decode_bounded`]
+    /// accepts.
+    fn skip_bounded(buf: &mut Reader<'_>, max_len: usize) -> Result<(), Self::Error>;
+}
+
+fn bounded_seq_len<T: Decodable>(buf: &mut Reader<'_>, max_len: usize) -> Result<usize, Error> {
+    let len = decode_seq_len::<T>(buf)?;
+    if len > max_len {
+        return Err(Error::TooLargeAlloc);
+    }
+    Ok(len)
+}
+
+impl<T> BoundedDecodable for Vec<T>
+where
+    T: Decodable,
+    T::Error: From<Error>,
+{
+    fn decode_bounded(buf: &mut Reader<'_>, max_len: usize) -> Result<Self, Self::Error> {
+        let len = bounded_seq_len::<T>(buf, max_len)?;
+        decode_vec(buf, len)
+    }
+
+    fn skip_bounded(buf: &mut Reader<'_>, max_len: usize) -> Result<(), Self::Error> {
+        let len = bounded_seq_len::<T>(buf, max_len)?;
+        skip_seq::<T>(buf, len)
+    }
+}
+
+impl BoundedDecodable for Bytes {
+    fn decode_bounded(buf: &mut Reader<'_>, max_len: usize) -> Result<Self, Self::Error> {
+        Ok(Bytes::copy_from_slice(buf.slice_bytes(max_len)?))
+    }
+
+    fn skip_bounded(buf: &mut Reader<'_>, max_len: usize) -> Result<(), Self::Error> {
+        buf.slice_bytes(max_len).map(drop)
+    }
+}
+
+impl BoundedDecodable for String {
+    fn decode_bounded(buf: &mut Reader<'_>, max_len: usize) -> Result<Self, Self::Error> {
+        Ok(core::str::from_utf8(buf.slice_bytes(max_len)?)?.into())
+    }
+
+    fn skip_bounded(buf: &mut Reader<'_>, max_len: usize) -> Result<(), Self::Error> {
+        core::str::from_utf8(buf.slice_bytes(max_len)?)?;
+        Ok(())
+    }
+}
+
+/// [`Vec`] of at most `MAX` items, encoded like a [`Vec`]. Decoding rejects longer ones
+/// before allocating.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref)]
+pub struct BoundedVec<T, const MAX: usize>(Vec<T>);
+
+impl<T, const MAX: usize> BoundedVec<T, MAX> {
+    pub const fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Appends an item, handing it back if the vector is full.
+    pub fn try_push(&mut self, item: T) -> Result<(), T> {
+        if self.0.len() >= MAX {
+            return Err(item);
+        }
+        self.0.push(item);
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<T, const MAX: usize> Default for BoundedVec<T, MAX> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const MAX: usize> TryFrom<Vec<T>> for BoundedVec<T, MAX> {
+    type Error = Error;
+
+    fn try_from(v: Vec<T>) -> Result<Self, Self::Error> {
+        if v.len() > MAX {
+            return Err(Error::TooLargeAlloc);
+        }
+        Ok(Self(v))
+    }
+}
+
+impl<T, const MAX: usize> From<BoundedVec<T, MAX>> for Vec<T> {
+    fn from(v: BoundedVec<T, MAX>) -> Self {
+        v.0
+    }
+}
+
+impl<T: Encodable, const MAX: usize> Encodable for BoundedVec<T, MAX> {
+    fn encode(&self, out: &mut Writer) {
+        encode_seq(&self.0, out)
+    }
+}
+
+impl<T, const MAX: usize> Decodable for BoundedVec<T, MAX>
+where
+    T: Decodable,
+    T::Error: From<Error>,
+{
+    type Error = T::Error;
+
+    fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
+        Vec::decode_bounded(buf, MAX).map(Self)
+    }
+
+    fn skip(buf: &mut Reader<'_>) -> Result<(), Self::Error> {
+        Vec::<T>::skip_bounded(buf, MAX)
+    }
+}
+
+impl<T: CserSchema, const MAX: usize> CserSchema for BoundedVec<T, MAX> {
+    fn schema_type(definitions: &mut Definitions) -> Type {
+        Vec::<T>::schema_type(definitions)
+    }
+}
+
+/// Byte string of at most `MAX` bytes, encoded like [`Bytes`]. Decoding rejects longer ones
+/// before copying them.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deref)]
+pub struct BoundedBytes<const MAX: usize>(Bytes);
+
+impl<const MAX: usize> BoundedBytes<MAX> {
+    pub fn into_inner(self) -> Bytes {
+        self.0
+    }
+}
+
+impl<const MAX: usize> TryFrom<Bytes> for BoundedBytes<MAX> {
+    type Error = Error;
+
+    fn try_from(v: Bytes) -> Result<Self, Self::Error> {
+        if v.len() > MAX {
+            return Err(Error::TooLargeAlloc);
+        }
+        Ok(Self(v))
+    }
+}
+
+impl<const MAX: usize> From<BoundedBytes<MAX>> for Bytes {
+    fn from(v: BoundedBytes<MAX>) -> Self {
+        v.0
+    }
+}
+
+impl<const MAX: usize> Encodable for BoundedBytes<MAX> {
+    fn encode(&self, out: &mut Writer) {
+        self.0.encode(out)
+    }
+}
+
+impl<const MAX: usize> Decodable for BoundedBytes<MAX> {
+    type Error = Error;
+
+    fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
+        Bytes::decode_bounded(buf, MAX).map(Self)
+    }
+
+    fn skip(buf: &mut Reader<'_>) -> Result<(), Self::Error> {
+        Bytes::skip_bounded(buf, MAX)
+    }
+}
+
+impl<const MAX: usize> CserSchema for BoundedBytes<MAX> {
+    fn schema_type(_: &mut Definitions) -> Type {
+        Type::Bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{binary::deserialize, testing::assert_roundtrip};
+
+    #[test]
+    fn bounded_vec() {
+        let v = BoundedVec::<u64, 2>::try_from(vec![1, u64::MAX]).unwrap();
+        let encoded = assert_roundtrip(&v);
+        assert_eq!(encoded, assert_roundtrip(&vec![1, u64::MAX]));
+
+        assert_eq!(
+            deserialize::<BoundedVec<u64, 1>>(&encoded),
+            Err(Error::TooLargeAlloc)
+        );
+        assert_eq!(
+            BoundedVec::<u64, 1>::try_from(vec![1, 2]),
+            Err(Error::TooLargeAlloc)
+        );
+
+        let mut v = BoundedVec::<u8, 1>::new();
+        assert_eq!(v.try_push(1), Ok(()));
+        assert_eq!(v.try_push(2), Err(2));
+        assert_eq!(assert_roundtrip(&v), assert_roundtrip(&vec![1_u8]));
+    }
+
+    #[test]
+    fn bounded_bytes() {
+        let v = BoundedBytes::<3>::try_from(Bytes::from_static(b"abc")).unwrap();
+        let encoded = assert_roundtrip(&v);
+        assert_eq!(encoded, assert_roundtrip(&Bytes::from_static(b"abc")));
+
+        assert_eq!(
+            deserialize::<BoundedBytes<2>>(&encoded),
+            Err(Error::TooLargeAlloc)
+        );
+        assert!(BoundedBytes::<2>::try_from(Bytes::from_static(b"abc")).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//! Length-framed CSER messages for [`tokio_util::codec`], same framing as [`crate::io`].
+
+use super::{
+    binary::deserialize,
+    error::Error,
+    io::{frame_len_prefix, parse_frame_len, CodecError, DEFAULT_MAX_FRAME_LEN, MAX_LEN_PREFIX},
+    Decodable, Encodable, Writer,
+};
+use bytes::{Buf, BufMut, BytesMut};
+use std::{convert::Infallible, marker::PhantomData};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Codec for messages of type `T`, each prefixed with its length as a compact integer.
+pub struct CserCodec<T> {
+    max_frame_len: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> CserCodec<T> {
+    pub fn new() -> Self {
+        Self::with_max_frame_len(DEFAULT_MAX_FRAME_LEN)
+    }
+
+    /// Creates a codec that rejects frames longer than `max_frame_len` in both directions.
+    pub fn with_max_frame_len(max_frame_len: usize) -> Self {
+        Self {
+            max_frame_len,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn max_frame_len(&self) -> usize {
+        self.max_frame_len
+    }
+}
+
+impl<T> Default for CserCodec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for CserCodec<T> {
+    fn clone(&self) -> Self {
+        Self::with_max_frame_len(self.max_frame_len)
+    }
+}
+
+impl<T> std::fmt::Debug for CserCodec<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CserCodec")
+            .field("max_frame_len", &self.max_frame_len)
+            .finish()
+    }
+}
+
+impl<T> Encoder<T> for CserCodec<T>
+where
+    T: Encodable,
+{
+    /// Encoding itself cannot fail, so only the frame size and I/O errors remain.
+    type Error = CodecError<Infallible>;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let mut w = Writer::new();
+        item.encode(&mut w);
+        let frame = w.output();
+
+        if frame.len() > self.max_frame_len {
+            return Err(CodecError::FrameTooLarge(frame.len() as u64));
+        }
+
+        let len = frame_len_prefix(frame.len());
+
+        dst.reserve(len.len() + frame.len());
+        dst.put_slice(&len);
+        dst.put_slice(&frame);
+
+        Ok(())
+    }
+}
+
+impl<T> Decoder for CserCodec<T>
+where
+    T: Decodable,
+    T::Error: From<Error>,
+{
+    type Item = T;
+    type Error = CodecError<T::Error>;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        // the last byte of the length carries the stop flag
+        let Some(prefix_len) = src
+            .iter()
+            .take(MAX_LEN_PREFIX)
+            .position(|b| b & 0b10000000 != 0)
+            .map(|pos| pos + 1)
+        else {
+            if src.len() >= MAX_LEN_PREFIX {
+                return Err(CodecError::Length(Error::MalformedEncoding));
+            }
+            return Ok(None);
+        };
+
+        let frame_len = parse_frame_len(&src[..prefix_len], self.max_frame_len)?;
+
+        if src.len() < prefix_len + frame_len {
+            src.reserve(prefix_len + frame_len - src.len());
+            return Ok(None);
+        }
+
+        src.advance(prefix_len);
+        let frame = src.split_to(frame_len);
+
+        deserialize(&frame).map(Some).map_err(CodecError::Decode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let mut codec = CserCodec::<Vec<u64>>::new();
+        let mut buf = BytesMut::new();
+
+        let items = [vec![], vec![1, 2, 3], vec![u64::MAX; 100]];
+        for item in items.clone() {
+            codec.encode(item, &mut buf).unwrap();
+        }
+
+        // feed the stream byte by byte
+        let mut src = BytesMut::new();
+        let mut decoded = vec![];
+        for b in buf {
+            src.put_u8(b);
+            if let Some(item) = codec.decode(&mut src).unwrap() {
+                decoded.push(item);
+            }
+        }
+
+        assert_eq!(decoded, items);
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn encode_borrowed() {
+        let items = vec![1_u64, 2, 3];
+        let mut buf = BytesMut::new();
+        CserCodec::<&Vec<u64>>::new()
+            .encode(&items, &mut buf)
+            .unwrap();
+
+        let decoded = CserCodec::<Vec<u64>>::new().decode(&mut buf).unwrap();
+        assert_eq!(decoded, Some(items));
+    }
+
+    #[test]
+    fn limits() {
+        let mut codec = CserCodec::<Vec<u8>>::with_max_frame_len(8);
+        let mut buf = BytesMut::new();
+
+        codec.encode(vec![0; 4], &mut buf).unwrap();
+        assert!(matches!(
+            codec.encode(vec![0; 8], &mut buf),
+            Err(CodecError::FrameTooLarge(11))
+        ));
+
+        // length is checked before the frame arrives
+        let mut src = BytesMut::from(&[0x89][..]);
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(CodecError::FrameTooLarge(9))
+        ));
+
+        // length without the stop flag
+        let mut src = BytesMut::from(&[0; MAX_LEN_PREFIX][..]);
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(CodecError::Length(Error::MalformedEncoding))
+        ));
+
+        // frame which is not a valid encoding
+        let mut src = BytesMut::from(&[0x81, 0x00][..]);
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(CodecError::Decode(Error::MalformedEncoding))
+        ));
+    }
+}
@@ -0,0 +1,1110 @@
+//! Decoding and encoding driven by a schema instead of Rust types, for tooling which does
+//! not have the types at hand.
+//!
+//! A schema is a list of Rust-like `struct` and `enum` definitions followed by the type of
+//! the whole blob:
+//!
+//! ```text
+//! // comments run to the end of the line
+//! struct Event {
+//!     epoch: u32,
+//!     parents: vec<[u8; 32]>,
+//!     vote: option<Vote>,
+//! }
+//!
+//! enum Vote {
+//!     Skip,
+//!     Epoch(u32, [u8; 32]),
+//!     Blocks { start: u64, votes: vec<[u8; 32]> },
+//! }
+//!
+//! vec<Event>
+//! ```
+//!
+//! Built-in types are `bool`, `u8`, `u16`, `u32`, `u64`, `i64`, `u56`, `u256` (with the
+//! `ethnum` feature), `bytes`, `string`, `[u8; N]`, `vec<T>`, `option<T>` and tuples. Each
+//! is laid out exactly like the corresponding native implementation. Structs are their
+//! fields in order, enums are the variant index as a `u32` followed by the variant fields.
+
+use super::{
+    binary::deserialize_cb_with, error::Strictness, Decodable, Encodable, Reader, Writer, U56,
+};
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet},
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use anyhow::{bail, ensure, format_err, Context};
+use core::{fmt, str::FromStr};
+
+/// Deepest nesting of values [`Schema::decode_type`] accepts, so that recursive types
+/// cannot exhaust the stack.
+pub const MAX_DEPTH: usize = 128;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Type {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    I64,
+    U56,
+    #[cfg(feature = "ethnum")]
+    U256,
+    Bytes,
+    String,
+    FixedBytes(usize),
+    Vec(Box<Type>),
+    Option(Box<Type>),
+    Tuple(Vec<Type>),
+    /// Struct or enum defined in the schema.
+    Named(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Fields {
+    Unit,
+    Tuple(Vec<Type>),
+    Named(Vec<(String, Type)>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Definition {
+    Struct(Fields),
+    Enum(Vec<(String, Fields)>),
+}
+
+/// Named types a schema refers to.
+pub type Definitions = BTreeMap<String, Definition>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schema {
+    pub definitions: Definitions,
+    pub root: Type,
+}
+
+/// Space a type takes in the bits and bytes sections, `None` where it depends on the value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub bits: Option<usize>,
+    pub bytes: Option<usize>,
+}
+
+impl Layout {
+    const EMPTY: Self = Self::fixed(0, 0);
+
+    const fn fixed(bits: usize, bytes: usize) -> Self {
+        Self {
+            bits: Some(bits),
+            bytes: Some(bytes),
+        }
+    }
+
+    /// Bits of the length or tag, followed by a variable number of bytes.
+    const fn prefixed(bits: usize) -> Self {
+        Self {
+            bits: Some(bits),
+            bytes: None,
+        }
+    }
+
+    fn then(self, next: Self) -> Self {
+        Self {
+            bits: self.bits.zip(next.bits).map(|(a, b)| a + b),
+            bytes: self.bytes.zip(next.bytes).map(|(a, b)| a + b),
+        }
+    }
+
+    /// Layout of something that appears zero or more times.
+    fn repeated(self) -> Self {
+        Self {
+            bits: self.bits.filter(|&bits| bits == 0),
+            bytes: self.bytes.filter(|&bytes| bytes == 0),
+        }
+    }
+}
+
+/// Decoded value. Unsigned integers of every width are [`Value::Uint`], `vec<u8>` is
+/// [`Value::Bytes`] like its native counterpart.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    Bool(bool),
+    Uint(u64),
+    Int(i64),
+    #[cfg(feature = "ethnum")]
+    U256(ethnum::U256),
+    Bytes(Vec<u8>),
+    String(String),
+    Option(Option<Box<Value>>),
+    /// Vector, tuple or tuple struct fields.
+    Seq(Vec<Value>),
+    /// Named struct fields in definition order.
+    Struct(Vec<(String, Value)>),
+    /// Enum variant with its fields, an empty [`Value::Seq`] for unit variants.
+    Variant(String, Box<Value>),
+}
+
+struct Parser<'a> {
+    s: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_ws(&mut self) {
+        loop {
+            self.s = self.s.trim_start();
+            match self.s.strip_prefix("//") {
+                Some(comment) => self.s = comment.find('\n').map_or("", |end| &comment[end..]),
+                None => break,
+            }
+        }
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_ws();
+        if let Some(rest) = self.s.strip_prefix(token) {
+            self.s = rest;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> anyhow::Result<()> {
+        ensure!(self.eat(token), "expected `{token}` at `{}`", self.head());
+        Ok(())
+    }
+
+    fn head(&self) -> &'a str {
+        self.s.lines().next().unwrap_or_default()
+    }
+
+    fn word(&mut self) -> anyhow::Result<&'a str> {
+        self.skip_ws();
+        let end = self
+            .s
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(self.s.len());
+        ensure!(end > 0, "expected identifier at `{}`", self.head());
+        let (word, rest) = self.s.split_at(end);
+        self.s = rest;
+        Ok(word)
+    }
+
+    /// Comma separated items up to `close`, trailing comma allowed.
+    fn list<T>(
+        &mut self,
+        close: &str,
+        mut item: impl FnMut(&mut Self) -> anyhow::Result<T>,
+    ) -> anyhow::Result<Vec<T>> {
+        let mut items = vec![];
+        while !self.eat(close) {
+            if !items.is_empty() {
+                self.expect(",")?;
+                if self.eat(close) {
+                    break;
+                }
+            }
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+
+    fn ty(&mut self) -> anyhow::Result<Type> {
+        if self.eat("(") {
+            return Ok(Type::Tuple(self.list(")", Self::ty)?));
+        }
+
+        if self.eat("[") {
+            self.expect("u8")?;
+            self.expect(";")?;
+            let len = self.word()?.parse().context("fixed bytes length")?;
+            self.expect("]")?;
+            return Ok(Type::FixedBytes(len));
+        }
+
+        let word = self.word()?;
+        if let Some(ty) = builtin(word) {
+            return Ok(ty);
+        }
+        Ok(match word {
+            "vec" | "option" => {
+                self.expect("<")?;
+                let inner = Box::new(self.ty()?);
+                self.expect(">")?;
+                if word == "vec" {
+                    Type::Vec(inner)
+                } else {
+                    Type::Option(inner)
+                }
+            }
+            name => Type::Named(name.to_string()),
+        })
+    }
+
+    fn named_field(&mut self) -> anyhow::Result<(String, Type)> {
+        let name = self.word()?.to_string();
+        self.expect(":")?;
+        Ok((name, self.ty()?))
+    }
+
+    fn fields(&mut self) -> anyhow::Result<Fields> {
+        Ok(if self.eat("(") {
+            Fields::Tuple(self.list(")", Self::ty)?)
+        } else if self.eat("{") {
+            Fields::Named(self.list("}", Self::named_field)?)
+        } else {
+            Fields::Unit
+        })
+    }
+
+    fn variant(&mut self) -> anyhow::Result<(String, Fields)> {
+        Ok((self.word()?.to_string(), self.fields()?))
+    }
+
+    fn schema(&mut self) -> anyhow::Result<Schema> {
+        let mut definitions = BTreeMap::new();
+        loop {
+            let rest = self.s;
+            let definition = match self.word() {
+                Ok("struct") => {
+                    let name = self.word()?;
+                    let fields = self.fields()?;
+                    if !matches!(fields, Fields::Named(_)) {
+                        self.expect(";")?;
+                    }
+                    (name, Definition::Struct(fields))
+                }
+                Ok("enum") => {
+                    let name = self.word()?;
+                    self.expect("{")?;
+                    (name, Definition::Enum(self.list("}", Self::variant)?))
+                }
+                _ => {
+                    self.s = rest;
+                    break;
+                }
+            };
+            let (name, definition) = definition;
+            ensure!(
+                builtin(name).is_none() && !matches!(name, "vec" | "option"),
+                "`{name}` is a built-in type"
+            );
+            ensure!(
+                definitions.insert(name.to_string(), definition).is_none(),
+                "`{name}` is defined twice"
+            );
+        }
+
+        let root = self.ty()?;
+        self.skip_ws();
+        ensure!(self.s.is_empty(), "trailing input `{}`", self.head());
+
+        let schema = Schema { definitions, root };
+        schema.check(&schema.root)?;
+        for definition in schema.definitions.values() {
+            let fields: Vec<_> = match definition {
+                Definition::Struct(fields) => vec![fields],
+                Definition::Enum(variants) => variants.iter().map(|(_, f)| f).collect(),
+            };
+            for ty in fields.into_iter().flat_map(Fields::types) {
+                schema.check(ty)?;
+            }
+        }
+        let mut done = BTreeSet::new();
+        for name in schema.definitions.keys() {
+            schema.check_cycle(name, &mut vec![], &mut done)?;
+        }
+        Ok(schema)
+    }
+}
+
+fn builtin(word: &str) -> Option<Type> {
+    Some(match word {
+        "bool" => Type::Bool,
+        "u8" => Type::U8,
+        "u16" => Type::U16,
+        "u32" => Type::U32,
+        "u64" => Type::U64,
+        "i64" => Type::I64,
+        "u56" => Type::U56,
+        #[cfg(feature = "ethnum")]
+        "u256" => Type::U256,
+        "bytes" => Type::Bytes,
+        "string" => Type::String,
+        _ => return None,
+    })
+}
+
+impl Type {
+    fn renamed(self, names: &BTreeMap<String, String>) -> Self {
+        match self {
+            Type::Vec(inner) => Type::Vec(Box::new(inner.renamed(names))),
+            Type::Option(inner) => Type::Option(Box::new(inner.renamed(names))),
+            Type::Tuple(types) => {
+                Type::Tuple(types.into_iter().map(|ty| ty.renamed(names)).collect())
+            }
+            Type::Named(name) => Type::Named(names.get(&name).cloned().unwrap_or(name)),
+            ty => ty,
+        }
+    }
+}
+
+impl Fields {
+    fn types(&self) -> Box<dyn Iterator<Item = &Type> + '_> {
+        match self {
+            Fields::Unit => Box::new(core::iter::empty()),
+            Fields::Tuple(types) => Box::new(types.iter()),
+            Fields::Named(fields) => Box::new(fields.iter().map(|(_, ty)| ty)),
+        }
+    }
+
+    fn renamed(self, names: &BTreeMap<String, String>) -> Self {
+        match self {
+            Fields::Unit => Fields::Unit,
+            Fields::Tuple(types) => {
+                Fields::Tuple(types.into_iter().map(|ty| ty.renamed(names)).collect())
+            }
+            Fields::Named(fields) => Fields::Named(
+                fields
+                    .into_iter()
+                    .map(|(name, ty)| (name, ty.renamed(names)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl FromStr for Type {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { s };
+        let ty = parser.ty()?;
+        parser.skip_ws();
+        ensure!(parser.s.is_empty(), "trailing input `{}`", parser.head());
+        Ok(ty)
+    }
+}
+
+impl FromStr for Schema {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parser { s }.schema()
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Bool => f.write_str("bool"),
+            Type::U8 => f.write_str("u8"),
+            Type::U16 => f.write_str("u16"),
+            Type::U32 => f.write_str("u32"),
+            Type::U64 => f.write_str("u64"),
+            Type::I64 => f.write_str("i64"),
+            Type::U56 => f.write_str("u56"),
+            #[cfg(feature = "ethnum")]
+            Type::U256 => f.write_str("u256"),
+            Type::Bytes => f.write_str("bytes"),
+            Type::String => f.write_str("string"),
+            Type::FixedBytes(len) => write!(f, "[u8; {len}]"),
+            Type::Vec(inner) => write!(f, "vec<{inner}>"),
+            Type::Option(inner) => write!(f, "option<{inner}>"),
+            Type::Tuple(types) => {
+                f.write_str("(")?;
+                write_list(f, types)?;
+                f.write_str(")")
+            }
+            Type::Named(name) => f.write_str(name),
+        }
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter<'_>, items: &[impl fmt::Display]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{item}")?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fields::Unit => Ok(()),
+            Fields::Tuple(types) => {
+                f.write_str("(")?;
+                write_list(f, types)?;
+                f.write_str(")")
+            }
+            Fields::Named(fields) => {
+                f.write_str(" { ")?;
+                for (name, ty) in fields {
+                    write!(f, "{name}: {ty}, ")?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, definition) in &self.definitions {
+            match definition {
+                Definition::Struct(fields @ Fields::Named(_)) => {
+                    writeln!(f, "struct {name}{fields}")?
+                }
+                Definition::Struct(fields) => writeln!(f, "struct {name}{fields};")?,
+                Definition::Enum(variants) => {
+                    writeln!(f, "enum {name} {{")?;
+                    for (variant, fields) in variants {
+                        writeln!(f, "    {variant}{fields},")?;
+                    }
+                    writeln!(f, "}}")?;
+                }
+            }
+        }
+        write!(f, "{}", self.root)
+    }
+}
+
+impl Schema {
+    /// Schema without definitions.
+    pub fn new(root: Type) -> Self {
+        Self {
+            definitions: BTreeMap::new(),
+            root,
+        }
+    }
+
+    fn check(&self, ty: &Type) -> anyhow::Result<()> {
+        match ty {
+            Type::Vec(inner) | Type::Option(inner) => self.check(inner),
+            Type::Tuple(types) => types.iter().try_for_each(|ty| self.check(ty)),
+            Type::Named(name) => self.definition(name).map(drop),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails if struct `name` contains itself without a tag, length or option in between.
+    /// Decoding such a struct would recurse forever without reading any input.
+    fn check_cycle<'a>(
+        &'a self,
+        name: &'a str,
+        visiting: &mut Vec<&'a str>,
+        done: &mut BTreeSet<&'a str>,
+    ) -> anyhow::Result<()> {
+        if done.contains(name) {
+            return Ok(());
+        }
+        ensure!(!visiting.contains(&name), "`{name}` contains itself");
+
+        if let Some(Definition::Struct(fields)) = self.definitions.get(name) {
+            visiting.push(name);
+            let mut types: Vec<_> = fields.types().collect();
+            while let Some(ty) = types.pop() {
+                match ty {
+                    Type::Tuple(inner) => types.extend(inner),
+                    Type::Named(inner) => self.check_cycle(inner, visiting, done)?,
+                    _ => {}
+                }
+            }
+            visiting.pop();
+        }
+        done.insert(name);
+        Ok(())
+    }
+
+    /// Renames definitions keyed by [`core::any::type_name`], as derived schemas are, to
+    /// names the schema language accepts. Each type gets the first of these that no other
+    /// type shares: the bare type name, the name followed by its type arguments, and the whole
+    /// path with its type arguments, joined with `_`.
+    pub(crate) fn with_short_names(self) -> Self {
+        fn words(s: &str) -> impl Iterator<Item = &str> {
+            s.split(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != ':')
+                .filter_map(|path| path.rsplit("::").next())
+                .filter(|word| !word.is_empty())
+        }
+        fn candidates(full: &str) -> [String; 3] {
+            let (path, args) = full.split_at(full.find('<').unwrap_or(full.len()));
+            let short = path.rsplit("::").next().unwrap_or(path);
+            let long = full
+                .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .filter(|word| !word.is_empty());
+            [
+                short.to_string(),
+                [short]
+                    .into_iter()
+                    .chain(words(args))
+                    .collect::<Vec<_>>()
+                    .join("_"),
+                long.collect::<Vec<_>>().join("_"),
+            ]
+        }
+
+        let candidates: BTreeMap<_, _> = self
+            .definitions
+            .keys()
+            .map(|full| (full, candidates(full)))
+            .collect();
+        let mut shared = BTreeMap::new();
+        for (level, name) in candidates.values().flat_map(|c| c.iter().enumerate()) {
+            *shared.entry((level, name)).or_insert(0) += 1;
+        }
+        let mut taken = BTreeSet::new();
+        let mut names = BTreeMap::new();
+        for full in self.definitions.keys() {
+            let [.., long] = &candidates[full];
+            let mut name = candidates[full]
+                .iter()
+                .enumerate()
+                .find(|&level| shared[&level] == 1)
+                .map_or(long, |(_, name)| name)
+                .clone();
+            while builtin(&name).is_some()
+                || matches!(name.as_str(), "vec" | "option")
+                || !taken.insert(name.clone())
+            {
+                name.push('_');
+            }
+            names.insert(full.clone(), name);
+        }
+
+        let definitions = self
+            .definitions
+            .into_iter()
+            .map(|(full, definition)| {
+                let definition = match definition {
+                    Definition::Struct(fields) => Definition::Struct(fields.renamed(&names)),
+                    Definition::Enum(variants) => Definition::Enum(
+                        variants
+                            .into_iter()
+                            .map(|(variant, fields)| (variant, fields.renamed(&names)))
+                            .collect(),
+                    ),
+                };
+                (names[&full].clone(), definition)
+            })
+            .collect();
+        Self {
+            definitions,
+            root: self.root.renamed(&names),
+        }
+    }
+
+    fn definition(&self, name: &str) -> anyhow::Result<&Definition> {
+        self.definitions
+            .get(name)
+            .ok_or_else(|| format_err!("unknown type `{name}`"))
+    }
+
+    /// Layout of `ty`, telling which sections it uses and whether its size is fixed.
+    pub fn layout(&self, ty: &Type) -> Layout {
+        self.layout_inner(ty, &mut vec![])
+    }
+
+    /// Layout of each field in encoding order, showing which section every field reads from.
+    pub fn field_layouts(&self, fields: &Fields) -> Vec<Layout> {
+        fields.types().map(|ty| self.layout(ty)).collect()
+    }
+
+    fn layout_inner<'a>(&'a self, ty: &'a Type, visiting: &mut Vec<&'a str>) -> Layout {
+        match ty {
+            Type::Bool => Layout::fixed(1, 0),
+            Type::U8 => Layout::fixed(0, 1),
+            Type::U16 => Layout::prefixed(1),
+            Type::U32 => Layout::prefixed(2),
+            Type::U64 | Type::U56 => Layout::prefixed(3),
+            Type::I64 => Layout::prefixed(4),
+            #[cfg(feature = "ethnum")]
+            Type::U256 => Layout::prefixed(3),
+            Type::Bytes | Type::String => Layout::prefixed(3),
+            Type::FixedBytes(len) => Layout::fixed(0, *len),
+            Type::Vec(inner) if **inner == Type::U8 => Layout::prefixed(3),
+            Type::Vec(inner) => {
+                Layout::prefixed(2).then(self.layout_inner(inner, visiting).repeated())
+            }
+            Type::Option(inner) => {
+                Layout::fixed(1, 0).then(self.layout_inner(inner, visiting).repeated())
+            }
+            Type::Tuple(types) => self.layout_fields(types.iter(), visiting),
+            // recursive types have no fixed size
+            Type::Named(name) if visiting.contains(&name.as_str()) => Layout {
+                bits: None,
+                bytes: None,
+            },
+            Type::Named(name) => {
+                visiting.push(name);
+                let layout = match self.definitions.get(name) {
+                    Some(Definition::Struct(fields)) => {
+                        self.layout_fields(fields.types(), visiting)
+                    }
+                    Some(Definition::Enum(variants)) => {
+                        let mut layouts = variants
+                            .iter()
+                            .map(|(_, fields)| self.layout_fields(fields.types(), visiting));
+                        let first = layouts.next().unwrap_or(Layout::EMPTY);
+                        let same = layouts.fold(first, |acc, l| Layout {
+                            bits: acc.bits.filter(|_| acc.bits == l.bits),
+                            bytes: acc.bytes.filter(|_| acc.bytes == l.bytes),
+                        });
+                        Layout::prefixed(2).then(same)
+                    }
+                    None => Layout {
+                        bits: None,
+                        bytes: None,
+                    },
+                };
+                visiting.pop();
+                layout
+            }
+        }
+    }
+
+    fn layout_fields<'a>(
+        &'a self,
+        types: impl Iterator<Item = &'a Type>,
+        visiting: &mut Vec<&'a str>,
+    ) -> Layout {
+        types.fold(Layout::EMPTY, |acc, ty| {
+            acc.then(self.layout_inner(ty, visiting))
+        })
+    }
+
+    /// Decodes a value of the root type.
+    pub fn decode(&self, r: &mut Reader<'_>) -> anyhow::Result<Value> {
+        self.decode_type(&self.root, r)
+    }
+
+    /// Encodes `v` as the root type.
+    pub fn encode(&self, v: &Value, w: &mut Writer) -> anyhow::Result<()> {
+        self.encode_type(&self.root, v, w)
+    }
+
+    pub fn decode_type(&self, ty: &Type, r: &mut Reader<'_>) -> anyhow::Result<Value> {
+        self.decode_nested(ty, r, 0)
+    }
+
+    fn decode_nested(&self, ty: &Type, r: &mut Reader<'_>, depth: usize) -> anyhow::Result<Value> {
+        ensure!(
+            depth < MAX_DEPTH,
+            "value is nested deeper than {MAX_DEPTH} levels"
+        );
+        let depth = depth + 1;
+        Ok(match ty {
+            Type::Bool => Value::Bool(bool::decode(r)?),
+            Type::U8 => Value::Uint(u8::decode(r)?.into()),
+            Type::U16 => Value::Uint(u16::decode(r)?.into()),
+            Type::U32 => Value::Uint(u32::decode(r)?.into()),
+            Type::U64 => Value::Uint(u64::decode(r)?),
+            Type::I64 => Value::Int(i64::decode(r)?),
+            Type::U56 => Value::Uint(*U56::decode(r)?),
+            #[cfg(feature = "ethnum")]
+            Type::U256 => Value::U256(ethnum::U256::decode(r)?),
+            Type::Bytes => Value::Bytes(Vec::decode(r)?),
+            Type::String => Value::String(String::decode(r)?),
+            Type::FixedBytes(len) => Value::Bytes(r.bytes_r.read(*len)?.to_vec()),
+            Type::Vec(inner) if **inner == Type::U8 => Value::Bytes(Vec::decode(r)?),
+            Type::Vec(inner) => {
+                let len = u32::decode(r)? as usize;
+                // every item takes at least a bit or a byte, unless it takes nothing at all
+                ensure!(
+                    len <= r.bits_r.non_read_bits() + r.bytes_r.remaining(),
+                    "{len} items do not fit in the rest of the input"
+                );
+                ensure!(
+                    len == 0 || self.layout(inner) != Layout::EMPTY,
+                    "vec<{inner}> holds {len} items of zero size"
+                );
+                Value::Seq(
+                    (0..len)
+                        .map(|_| self.decode_nested(inner, r, depth))
+                        .collect::<anyhow::Result<_>>()?,
+                )
+            }
+            Type::Option(inner) => Value::Option(if bool::decode(r)? {
+                Some(Box::new(self.decode_nested(inner, r, depth)?))
+            } else {
+                None
+            }),
+            Type::Tuple(types) => Value::Seq(
+                types
+                    .iter()
+                    .map(|ty| self.decode_nested(ty, r, depth))
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+            Type::Named(name) => match self.definition(name)? {
+                Definition::Struct(fields) => self.decode_fields(fields, r, depth)?,
+                Definition::Enum(variants) => {
+                    let index = u32::decode(r)?;
+                    let (variant, fields) = variants
+                        .get(index as usize)
+                        .ok_or_else(|| format_err!("`{name}` has no variant {index}"))?;
+                    Value::Variant(
+                        variant.clone(),
+                        Box::new(self.decode_fields(fields, r, depth)?),
+                    )
+                }
+            },
+        })
+    }
+
+    fn decode_fields(
+        &self,
+        fields: &Fields,
+        r: &mut Reader<'_>,
+        depth: usize,
+    ) -> anyhow::Result<Value> {
+        Ok(match fields {
+            Fields::Named(fields) => Value::Struct(
+                fields
+                    .iter()
+                    .map(|(name, ty)| Ok((name.clone(), self.decode_nested(ty, r, depth)?)))
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+            fields => Value::Seq(
+                fields
+                    .types()
+                    .map(|ty| self.decode_nested(ty, r, depth))
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+        })
+    }
+
+    pub fn encode_type(&self, ty: &Type, v: &Value, w: &mut Writer) -> anyhow::Result<()> {
+        match (ty, v) {
+            (Type::Bool, Value::Bool(v)) => v.encode(w),
+            (Type::U8, Value::Uint(v)) => uint::<u8>(*v)?.encode(w),
+            (Type::U16, Value::Uint(v)) => uint::<u16>(*v)?.encode(w),
+            (Type::U32, Value::Uint(v)) => uint::<u32>(*v)?.encode(w),
+            (Type::U64, Value::Uint(v)) => v.encode(w),
+            (Type::I64, Value::Int(v)) => v.encode(w),
+            (Type::U56, Value::Uint(v)) => U56::try_from(*v)
+                .map_err(|_| format_err!("{v} is out of range"))?
+                .encode(w),
+            #[cfg(feature = "ethnum")]
+            (Type::U256, Value::U256(v)) => v.encode(w),
+            (Type::Bytes, Value::Bytes(v)) => v.as_slice().encode(w),
+            (Type::String, Value::String(v)) => v.encode(w),
+            (Type::FixedBytes(len), Value::Bytes(v)) => {
+                ensure!(v.len() == *len, "expected {len} bytes, got {}", v.len());
+                w.bytes_w.write(v);
+            }
+            (Type::Vec(inner), Value::Bytes(v)) if **inner == Type::U8 => v.as_slice().encode(w),
+            (Type::Vec(inner), Value::Seq(items)) if **inner != Type::U8 => {
+                u32::try_from(items.len())?.encode(w);
+                for item in items {
+                    self.encode_type(inner, item, w)?;
+                }
+            }
+            (Type::Option(inner), Value::Option(v)) => {
+                v.is_some().encode(w);
+                if let Some(v) = v {
+                    self.encode_type(inner, v, w)?;
+                }
+            }
+            (Type::Tuple(types), Value::Seq(items)) => self.encode_seq(types, items, w)?,
+            (Type::Named(name), v) => match (self.definition(name)?, v) {
+                (Definition::Struct(fields), v) => self.encode_fields(fields, v, w)?,
+                (Definition::Enum(variants), Value::Variant(variant, v)) => {
+                    let index = variants
+                        .iter()
+                        .position(|(name, _)| name == variant)
+                        .ok_or_else(|| format_err!("`{name}` has no variant `{variant}`"))?;
+                    u32::try_from(index)?.encode(w);
+                    self.encode_fields(&variants[index].1, v, w)?;
+                }
+                _ => bail!("expected a variant of `{name}`, got {v:?}"),
+            },
+            (ty, v) => bail!("expected {ty}, got {v:?}"),
+        }
+
+        Ok(())
+    }
+
+    fn encode_seq(&self, types: &[Type], items: &[Value], w: &mut Writer) -> anyhow::Result<()> {
+        ensure!(
+            items.len() == types.len(),
+            "expected {} fields, got {}",
+            types.len(),
+            items.len()
+        );
+        for (ty, item) in types.iter().zip(items) {
+            self.encode_type(ty, item, w)?;
+        }
+        Ok(())
+    }
+
+    fn encode_fields(&self, fields: &Fields, v: &Value, w: &mut Writer) -> anyhow::Result<()> {
+        match (fields, v) {
+            (Fields::Unit, Value::Seq(items)) => self.encode_seq(&[], items, w),
+            (Fields::Tuple(types), Value::Seq(items)) => self.encode_seq(types, items, w),
+            (Fields::Named(fields), Value::Struct(items)) => {
+                ensure!(
+                    items.len() == fields.len()
+                        && fields.iter().zip(items).all(|((a, _), (b, _))| a == b),
+                    "expected fields {:?}, got {:?}",
+                    fields.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+                    items.iter().map(|(name, _)| name).collect::<Vec<_>>()
+                );
+                for ((_, ty), (_, item)) in fields.iter().zip(items) {
+                    self.encode_type(ty, item, w)?;
+                }
+                Ok(())
+            }
+            (fields, v) => bail!("expected fields{fields}, got {v:?}"),
+        }
+    }
+}
+
+fn uint<T: TryFrom<u64>>(v: u64) -> anyhow::Result<T> {
+    v.try_into().map_err(|_| format_err!("{v} is out of range"))
+}
+
+/// Decodes a whole blob as the root type of `schema`.
+pub fn decode_with_schema(schema: &Schema, input: &[u8]) -> anyhow::Result<Value> {
+    deserialize_cb_with(input, Strictness::default(), |r| schema.decode(r)).map(|(v, _)| v)
+}
+
+/// Encodes `v` as the root type of `schema`.
+pub fn encode_with_schema(schema: &Schema, v: &Value) -> anyhow::Result<Vec<u8>> {
+    let mut w = Writer::new();
+    schema.encode(v, &mut w)?;
+    Ok(w.output())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    const SCHEMA: &str = "
+        // single line comment
+        struct Header {
+            epoch: u32,
+            parents: vec<[u8; 4]>,
+            vote: option<Vote>,
+        }
+
+        struct Id(u56);
+        struct Marker;
+
+        enum Vote {
+            Skip,
+            Epoch(u32, Id),
+            Blocks { start: u64, votes: vec<Id>, },
+        }
+
+        (vec<Header>, Marker, i64, bytes, string)
+    ";
+
+    #[test]
+    fn parse() {
+        let schema = SCHEMA.parse::<Schema>().unwrap();
+        assert_eq!(schema.definitions.len(), 4);
+        assert_eq!(
+            schema.definitions["Vote"],
+            Definition::Enum(vec![
+                ("Skip".into(), Fields::Unit),
+                (
+                    "Epoch".into(),
+                    Fields::Tuple(vec![Type::U32, Type::Named("Id".into())])
+                ),
+                (
+                    "Blocks".into(),
+                    Fields::Named(vec![
+                        ("start".into(), Type::U64),
+                        (
+                            "votes".into(),
+                            Type::Vec(Box::new(Type::Named("Id".into())))
+                        ),
+                    ])
+                ),
+            ])
+        );
+        assert_eq!(schema.to_string().parse::<Schema>().unwrap(), schema);
+
+        assert_eq!(
+            "(u32, vec<option<u64>>, [u8; 32])".parse::<Type>().unwrap(),
+            Type::Tuple(vec![
+                Type::U32,
+                Type::Vec(Box::new(Type::Option(Box::new(Type::U64)))),
+                Type::FixedBytes(32),
+            ])
+        );
+
+        for invalid in [
+            "vec<u64",
+            "u64 u64",
+            "Unknown",
+            "struct A { x: B } A",
+            "struct A; struct A; A",
+            "struct u8; u8",
+            "enum E { A } ",
+            "struct A { x: A } A",
+            "struct A(u8, (B, u8)); struct B { a: A } vec<B>",
+        ] {
+            assert!(invalid.parse::<Schema>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn matches_native() {
+        let schema = SCHEMA.parse::<Schema>().unwrap();
+        let v = Value::Seq(vec![
+            Value::Seq(vec![
+                Value::Struct(vec![
+                    ("epoch".into(), Value::Uint(7)),
+                    ("parents".into(), Value::Seq(vec![])),
+                    ("vote".into(), Value::Option(None)),
+                ]),
+                Value::Struct(vec![
+                    ("epoch".into(), Value::Uint(8)),
+                    ("parents".into(), Value::Seq(vec![Value::Bytes(vec![1; 4])])),
+                    (
+                        "vote".into(),
+                        Value::Option(Some(Box::new(Value::Variant(
+                            "Blocks".into(),
+                            Box::new(Value::Struct(vec![
+                                ("start".into(), Value::Uint(300)),
+                                (
+                                    "votes".into(),
+                                    Value::Seq(vec![Value::Seq(vec![Value::Uint(1 << 40)])]),
+                                ),
+                            ])),
+                        )))),
+                    ),
+                ]),
+            ]),
+            Value::Seq(vec![]),
+            Value::Int(-5),
+            Value::Bytes(vec![0x42, 0x43]),
+            Value::String("hi".into()),
+        ]);
+
+        let mut native = Writer::new();
+        2_u32.encode(&mut native);
+        7_u32.encode(&mut native);
+        Vec::<[u8; 4]>::new().encode(&mut native);
+        false.encode(&mut native);
+        8_u32.encode(&mut native);
+        vec![[1_u8; 4]].encode(&mut native);
+        true.encode(&mut native);
+        2_u32.encode(&mut native);
+        300_u64.encode(&mut native);
+        vec![U56::try_from(1 << 40).unwrap()].encode(&mut native);
+        (-5_i64).encode(&mut native);
+        Bytes::from_static(&[0x42, 0x43]).encode(&mut native);
+        "hi".to_string().encode(&mut native);
+        let native = native.output();
+
+        assert_eq!(encode_with_schema(&schema, &v).unwrap(), native);
+        assert_eq!(decode_with_schema(&schema, &native).unwrap(), v);
+    }
+
+    #[test]
+    fn layout() {
+        let schema = "
+            struct Id([u8; 32]);
+            struct Node { id: Id, next: option<Node> }
+            enum Tag { A(bool, [u8; 2]), B { c: bool, d: u8, e: u8 } }
+            (Id, u32, option<bool>, vec<Id>, Tag, Node)
+        "
+        .parse::<Schema>()
+        .unwrap();
+        let Type::Tuple(types) = &schema.root else {
+            unreachable!()
+        };
+        let layouts: Vec<_> = types.iter().map(|ty| schema.layout(ty)).collect();
+        assert_eq!(
+            layouts,
+            [
+                Layout::fixed(0, 32),
+                Layout::prefixed(2),
+                Layout {
+                    bits: None,
+                    bytes: Some(0)
+                },
+                Layout::prefixed(2),
+                Layout::prefixed(3),
+                Layout {
+                    bits: None,
+                    bytes: None
+                },
+            ]
+        );
+        assert_eq!(schema.layout(&schema.root).bits, None);
+
+        let Definition::Enum(variants) = &schema.definitions["Tag"] else {
+            unreachable!()
+        };
+        assert_eq!(
+            schema.field_layouts(&variants[1].1),
+            [
+                Layout::fixed(1, 0),
+                Layout::fixed(0, 1),
+                Layout::fixed(0, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn mismatch() {
+        let schema = Schema::new("(u8, [u8; 2])".parse().unwrap());
+        for invalid in [
+            Value::Seq(vec![Value::Uint(256), Value::Bytes(vec![0; 2])]),
+            Value::Seq(vec![Value::Uint(1), Value::Bytes(vec![0; 3])]),
+            Value::Seq(vec![Value::Int(1), Value::Bytes(vec![0; 2])]),
+            Value::Seq(vec![Value::Uint(1)]),
+        ] {
+            assert!(
+                encode_with_schema(&schema, &invalid).is_err(),
+                "{invalid:?}"
+            );
+        }
+
+        let schema = "enum E { A, B } E".parse::<Schema>().unwrap();
+        assert!(decode_with_schema(&schema, &[0x02, 0x00, 0x81]).is_err());
+        assert_eq!(
+            decode_with_schema(&schema, &[0x01, 0x00, 0x81]).unwrap(),
+            Value::Variant("B".into(), Box::new(Value::Seq(vec![])))
+        );
+    }
+
+    #[test]
+    fn untrusted_input() {
+        // recursion which reads something on every level is fine
+        "struct A { x: option<A>, y: vec<A> } enum E { X(E), Y } (A, E)"
+            .parse::<Schema>()
+            .unwrap();
+
+        // 2^32 - 1 items of zero size
+        let schema = "vec<()>".parse::<Schema>().unwrap();
+        let err = decode_with_schema(&schema, &[0xff, 0xff, 0xff, 0xff, 0x03, 0x81]).unwrap_err();
+        assert!(err.to_string().contains("do not fit"), "{err}");
+        let err = decode_with_schema(&schema, &[0x01, 0x00, 0x81]).unwrap_err();
+        assert!(err.to_string().contains("zero size"), "{err}");
+
+        let schema = "struct Node { next: option<Node> } Node"
+            .parse::<Schema>()
+            .unwrap();
+        let nested = |depth| {
+            (0..depth).fold(Value::Option(None), |v, _| {
+                Value::Option(Some(Box::new(Value::Struct(vec![("next".into(), v)]))))
+            })
+        };
+        let wrap = |v| Value::Struct(vec![("next".into(), v)]);
+        let ok = encode_with_schema(&schema, &wrap(nested(MAX_DEPTH / 2 - 1))).unwrap();
+        assert!(decode_with_schema(&schema, &ok).is_ok());
+        let deep = encode_with_schema(&schema, &wrap(nested(MAX_DEPTH))).unwrap();
+        let err = decode_with_schema(&schema, &deep).unwrap_err();
+        assert!(err.to_string().contains("nested deeper"), "{err}");
+    }
+}
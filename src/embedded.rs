@@ -0,0 +1,187 @@
+use super::{
+    binary::{binary_to_cser, check_end},
+    dynamic::{Definitions, Type},
+    error::{Error, Violation},
+    imp::read_bytes,
+    CserSchema, Decodable, Encodable, Reader, Writer,
+};
+use derive_more::{Deref, From};
+
+/// Value encoded as a complete blob of its own, with its own bits section and footer,
+/// stored in the parent as a length prefixed byte string.
+///
+/// Whatever the blob holds after the fields `T` reads is a tolerated violation, so that
+/// fields appended by newer versions of `T` break older decoders in strict mode only.
+/// Decoders which do not know `T` at all can skip it as a plain byte string.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From)]
+pub struct Embedded<T>(pub T);
+
+impl<T: Encodable> Encodable for Embedded<T> {
+    fn encode(&self, out: &mut Writer) {
+        let mut w = Writer::new();
+        self.0.encode(&mut w);
+        w.output().as_slice().encode(out)
+    }
+}
+
+impl<T> Embedded<T>
+where
+    T: Decodable,
+    T::Error: From<Error>,
+{
+    fn read<R>(
+        buf: &mut Reader<'_>,
+        f: impl FnOnce(&mut Reader<'_>) -> Result<R, T::Error>,
+    ) -> Result<R, T::Error> {
+        let (bbits, bbytes, canonical_footer) = binary_to_cser(read_bytes(buf)?)?;
+
+        let mut inner = Reader::new(bbits, bbytes);
+        inner.strictness = buf.strictness;
+        if !canonical_footer {
+            inner.non_canonical(Violation::FooterSize)?;
+        }
+        let out = f(&mut inner)?;
+        check_end(&mut inner, true)?;
+
+        buf.extend_violations(inner.take_violations());
+        Ok(out)
+    }
+}
+
+impl<T> Decodable for Embedded<T>
+where
+    T: Decodable,
+    T::Error: From<Error>,
+{
+    type Error = T::Error;
+
+    fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
+        Self::read(buf, T::decode).map(Self)
+    }
+
+    fn skip(buf: &mut Reader<'_>) -> Result<(), Self::Error> {
+        Self::read(buf, T::skip)
+    }
+}
+
+/// The schema describes the outer byte string, the blob inside is opaque to it.
+impl<T> CserSchema for Embedded<T> {
+    fn schema_type(_: &mut Definitions) -> Type {
+        Type::Bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        binary::{check_canonical, deserialize, deserialize_cb_with, deserialize_with},
+        error::Strictness,
+        testing::assert_roundtrip,
+    };
+
+    #[derive(Debug, PartialEq)]
+    struct V1 {
+        a: u32,
+        b: bool,
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct V2 {
+        a: u32,
+        b: bool,
+        c: Vec<u64>,
+        d: bool,
+    }
+
+    impl Encodable for V2 {
+        fn encode(&self, out: &mut Writer) {
+            self.a.encode(out);
+            self.b.encode(out);
+            self.c.encode(out);
+            self.d.encode(out);
+        }
+    }
+
+    impl Decodable for V1 {
+        type Error = Error;
+
+        fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
+            Ok(Self {
+                a: u32::decode(buf)?,
+                b: bool::decode(buf)?,
+            })
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        assert_roundtrip(&Embedded(vec![Some(1_u64), None]));
+        assert_roundtrip(&vec![Embedded(u64::MAX), Embedded(0)]);
+    }
+
+    #[test]
+    fn newer_payload() {
+        let v2 = V2 {
+            a: 7,
+            b: true,
+            c: vec![1, 2, 3],
+            d: true,
+        };
+        let mut w = Writer::new();
+        Embedded(&v2).encode(&mut w);
+        0x1234_u16.encode(&mut w);
+        let input = w.output();
+
+        let decode = |strictness| {
+            deserialize_cb_with::<_, Error>(&input, strictness, |r| {
+                Ok((Embedded::<V1>::decode(r)?, u16::decode(r)?))
+            })
+        };
+        let ((v1, next), violations) = decode(Strictness::Compatible).unwrap();
+        assert_eq!(*v1, V1 { a: 7, b: true });
+        assert_eq!(next, 0x1234);
+        assert_eq!(
+            violations,
+            [
+                Violation::UnusedBitsBytes { count: 1 },
+                Violation::NonZeroPadding,
+                Violation::TrailingBytes { count: 4 }
+            ]
+        );
+        assert!(decode(Strictness::Strict).is_err());
+    }
+
+    #[test]
+    fn trailing_junk() {
+        let mut inner = Writer::new();
+        7_u64.encode(&mut inner);
+        let mut blob = inner.output();
+        // a junk byte between the bytes section and the bits section
+        let footer_at = blob.len() - 2;
+        blob.insert(footer_at, 0xaa);
+        let mut w = Writer::new();
+        blob.as_slice().encode(&mut w);
+        let input = w.output();
+
+        assert!(deserialize_with::<Embedded<u64>>(&input, Strictness::Strict).is_err());
+        assert_eq!(
+            check_canonical::<Embedded<u64>>(&input).unwrap(),
+            [Violation::TrailingBytes { count: 1 }]
+        );
+    }
+
+    #[test]
+    fn inner_violations() {
+        let mut w = Writer::new();
+        // zero padded integer inside the embedded blob
+        [0x01_u8, 0x00, 0x01, 0x81].as_slice().encode(&mut w);
+        let input = w.output();
+
+        assert!(deserialize::<Embedded<u64>>(&input).is_err());
+        let (v, violations) =
+            deserialize_with::<Embedded<u64>>(&input, Strictness::Lenient).unwrap();
+        assert_eq!(*v, 1);
+        assert_eq!(violations, [Violation::IntegerZeroHighByte { offset: 0 }]);
+    }
+}
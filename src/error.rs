@@ -11,3 +11,39 @@ pub enum Error {
     #[error("custom error")]
     Custom(&'static str),
 }
+
+/// How decoding treats encodings which are valid but not canonical.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strictness {
+    /// Reject them all with [`Error::NonCanonicalEncoding`].
+    Strict,
+    /// Accept exactly what go-opera's `utils/cser` accepts: reject the ones it rejects and
+    /// collect a [`Violation`] for the ones it tolerates.
+    #[default]
+    Compatible,
+    /// Accept them all and collect a [`Violation`] for each.
+    Lenient,
+}
+
+/// Deviation from the canonical encoding. Offsets are positions in the bytes section.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Violation {
+    #[error("bits size in the footer has a redundant zero group")]
+    FooterSize,
+    #[error("integer at offset {offset} ends with a zero byte")]
+    IntegerZeroHighByte { offset: usize },
+    #[error("negative zero integer at offset {offset}")]
+    NegativeZero { offset: usize },
+    #[error("signed integer at offset {offset} wraps around")]
+    SignedOverflow { offset: usize },
+    #[error("big integer at offset {offset} has leading zero bytes")]
+    BigIntegerLeadingZero { offset: usize },
+    #[error("{count} unused bytes at the end of the bits section")]
+    UnusedBitsBytes { count: usize },
+    #[error("padding of the bits section is not zero")]
+    NonZeroPadding,
+    #[error("{count} unread bytes at the end of the bytes section")]
+    TrailingBytes { count: usize },
+    #[error("{0}")]
+    Custom(&'static str),
+}
@@ -1,4 +1,5 @@
 use super::error::Error;
+use alloc::vec::Vec;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Reader<'a> {
@@ -44,6 +45,21 @@ impl<'a> Reader<'a> {
         self.offset
     }
 
+    // Moves the cursor to `offset`.
+    pub(crate) fn seek(&mut self, offset: usize) -> Result<(), Error> {
+        if offset > self.buf.len() {
+            return Err(Error::MalformedEncoding);
+        }
+
+        self.offset = offset;
+        Ok(())
+    }
+
+    // Number of bytes not consumed yet.
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.offset
+    }
+
     // Empty returns true if the whole buffer is consumed
     pub fn empty(&self) -> bool {
         self.buf.len() == self.offset
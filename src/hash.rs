@@ -0,0 +1,71 @@
+//! Hashing of CSER encodings without concatenating them into a single buffer.
+
+use super::{binary::bits_size_footer, Encodable, Writer};
+use digest::{Digest, Output};
+
+/// Feeds the encoding collected by `w` into `hasher`: the bytes section, the bits section
+/// and the footer, exactly as they appear in [`Writer::output`].
+pub fn update<D: Digest>(hasher: &mut D, w: &Writer) {
+    let bbits = w.bits_w.view_bytes();
+
+    hasher.update(&w.bytes_w.buf);
+    hasher.update(bbits);
+    hasher.update(bits_size_footer(bbits.len()));
+}
+
+/// Hashes the encoding of `value` with `D`.
+pub fn digest<D: Digest>(value: &(impl Encodable + ?Sized)) -> Output<D> {
+    let mut w = Writer::new();
+    value.encode(&mut w);
+
+    let mut hasher = D::new();
+    update(&mut hasher, &w);
+    hasher.finalize()
+}
+
+/// Content hashes of the encoding, available for every `Encodable` type.
+pub trait Hashable: Encodable {
+    #[cfg(feature = "sha3")]
+    fn cser_keccak256(&self) -> [u8; 32] {
+        digest::<sha3::Keccak256>(self).into()
+    }
+
+    #[cfg(feature = "sha2")]
+    fn cser_sha256(&self) -> [u8; 32] {
+        digest::<sha2::Sha256>(self).into()
+    }
+}
+
+impl<T: Encodable + ?Sized> Hashable for T {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check<D: Digest>(value: &impl Encodable) {
+        let mut w = Writer::new();
+        value.encode(&mut w);
+
+        assert_eq!(digest::<D>(value), D::digest(w.output()));
+    }
+
+    #[test]
+    fn same_as_output() {
+        for value in [vec![], vec![0_u64], vec![1, u64::MAX, 0xAABB]] {
+            check::<sha2::Sha256>(&value);
+            check::<sha3::Keccak256>(&value);
+            assert_eq!(
+                value.cser_keccak256(),
+                <[u8; 32]>::from(digest::<sha3::Keccak256>(&value))
+            );
+            assert_eq!(
+                value.cser_sha256(),
+                <[u8; 32]>::from(digest::<sha2::Sha256>(&value))
+            );
+        }
+
+        // the bits section is large enough for a multi-byte footer
+        let bools = vec![true; 2000];
+        check::<sha3::Keccak256>(&bools);
+    }
+}
@@ -1,12 +1,16 @@
 use super::{
-    error::Error,
+    dynamic::{Definitions, Type},
+    error::{Error, Violation},
     read_writer::{Reader, Writer},
-    Decodable, Encodable, U56,
+    BitPacked, CserSchema, Decodable, Encodable, U56,
 };
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
 use bytes::Bytes;
-use std::any::Any;
+use core::marker::PhantomData;
 
 impl Encodable for u8 {
+    const IS_BYTE: bool = true;
+
     fn encode(&self, out: &mut Writer) {
         out.bytes_w.write(&[*self])
     }
@@ -15,6 +19,8 @@ impl Encodable for u8 {
 impl Decodable for u8 {
     type Error = Error;
 
+    const FROM_BYTE: Option<fn(u8) -> Self> = Some(|b| b);
+
     fn decode(buf: &mut Reader<'_>) -> anyhow::Result<Self, Self::Error> {
         buf.bytes_r.read_byte()
     }
@@ -30,7 +36,7 @@ impl Decodable for bool {
     type Error = Error;
 
     fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
-        Ok(u8::try_from(buf.bits_r.read(1)).map_err(|_| Error::OverFlowError)? != 0)
+        Ok(u8::try_from(buf.bits_r.read(1)?).map_err(|_| Error::OverFlowError)? != 0)
     }
 }
 
@@ -92,13 +98,19 @@ impl Decodable for i64 {
 
     fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
         let neg = bool::decode(buf)?;
+        let offset = buf.bytes_r.position();
         let abs = u64::decode(buf)?;
         if neg && abs == 0 {
-            return Err(Error::NonCanonicalEncoding);
+            buf.non_canonical(Violation::NegativeZero { offset })?;
         }
-        let mut v = 0_i64.overflowing_add_unsigned(abs).0;
-        if neg {
-            v = v.overflowing_neg().0;
+        // go-opera wraps magnitudes which do not fit around
+        let (v, overflow) = if neg {
+            0_i64.overflowing_sub_unsigned(abs)
+        } else {
+            0_i64.overflowing_add_unsigned(abs)
+        };
+        if overflow {
+            buf.tolerated_non_canonical(Violation::SignedOverflow { offset })?;
         }
         Ok(v)
     }
@@ -118,6 +130,38 @@ impl Decodable for U56 {
     }
 }
 
+impl BitPacked for bool {
+    fn to_bits(&self) -> u64 {
+        (*self).into()
+    }
+
+    fn from_bits(v: u64) -> Option<Self> {
+        match v {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_bit_packed {
+    ($($ty:ty),*) => {
+        $(
+            impl BitPacked for $ty {
+                fn to_bits(&self) -> u64 {
+                    (*self).into()
+                }
+
+                fn from_bits(v: u64) -> Option<Self> {
+                    v.try_into().ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_bit_packed!(u8, u16, u32, u64);
+
 impl<T> Encodable for Option<T>
 where
     T: Encodable,
@@ -151,6 +195,35 @@ where
             None
         })
     }
+
+    fn skip(buf: &mut Reader<'_>) -> Result<(), Self::Error> {
+        if bool::decode(buf)? {
+            T::skip(buf)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: ?Sized> Encodable for PhantomData<T> {
+    fn encode(&self, _: &mut Writer) {}
+}
+
+impl<T: ?Sized> Decodable for PhantomData<T> {
+    type Error = Error;
+
+    fn decode(_: &mut Reader<'_>) -> Result<Self, Self::Error> {
+        Ok(PhantomData)
+    }
+}
+
+/// Reads a byte string prefixed with its length.
+pub(crate) fn read_bytes<'a>(buf: &mut Reader<'a>) -> Result<&'a [u8], Error> {
+    let len = U56::decode(buf)?
+        .0
+        .try_into()
+        .map_err(|_| Error::OverFlowError)?;
+
+    buf.bytes_r.read(len)
 }
 
 impl Encodable for &[u8] {
@@ -179,50 +252,107 @@ impl Decodable for Bytes {
 
         Ok(Bytes::copy_from_slice(buf.bytes_r.read(len)?))
     }
+
+    fn skip(buf: &mut Reader<'_>) -> Result<(), Self::Error> {
+        read_bytes(buf).map(drop)
+    }
+}
+
+/// Writes a sequence prefixed with its length, see [`Encodable::IS_BYTE`].
+pub(crate) fn encode_seq<T: Encodable>(items: &[T], out: &mut Writer) {
+    if T::IS_BYTE {
+        U56::try_from(u64::try_from(items.len()).unwrap())
+            .unwrap()
+            .encode(out)
+    } else {
+        u32::try_from(items.len()).unwrap().encode(out)
+    }
+    for item in items {
+        item.encode(out)
+    }
+}
+
+/// Reads the length of a sequence, checking that byte strings are actually present.
+pub(crate) fn decode_seq_len<T: Decodable>(buf: &mut Reader<'_>) -> Result<usize, Error> {
+    if T::FROM_BYTE.is_some() {
+        let len = U56::decode(buf)?
+            .0
+            .try_into()
+            .map_err(|_| Error::OverFlowError)?;
+        if len > buf.bytes_r.remaining() {
+            return Err(Error::MalformedEncoding);
+        }
+        Ok(len)
+    } else {
+        Ok(usize::try_from(u32::decode(buf)?).unwrap())
+    }
+}
+
+/// Capacity to preallocate for a sequence of `len` items. The length is untrusted, so this
+/// is no more than the rest of the input could possibly hold.
+fn capacity_hint(buf: &Reader<'_>, len: usize) -> usize {
+    len.min(buf.bits_r.non_read_bits() + buf.bytes_r.remaining())
 }
 
-impl<T> Encodable for Vec<T>
+/// Advances past a sequence of `len` items.
+pub(crate) fn skip_seq<T>(buf: &mut Reader<'_>, len: usize) -> Result<(), T::Error>
 where
-    T: Encodable + 'static,
+    T: Decodable,
+    T::Error: From<Error>,
 {
-    fn encode(&self, out: &mut Writer) {
-        if let Some(s) = <dyn Any>::downcast_ref::<Vec<u8>>(self) {
-            s.as_slice().encode(out)
-        } else {
-            u32::try_from(self.len()).unwrap().encode(out);
-            for item in self {
-                item.encode(out)
-            }
+    if T::FROM_BYTE.is_some() {
+        buf.bytes_r.read(len)?;
+    } else {
+        for _ in 0..len {
+            T::skip(buf)?;
         }
     }
+    Ok(())
 }
 
-impl<T> Decodable for Vec<T>
+/// Reads `len` items.
+pub(crate) fn decode_vec<T>(buf: &mut Reader<'_>, len: usize) -> Result<Vec<T>, T::Error>
 where
-    T: Decodable + 'static,
+    T: Decodable,
     T::Error: From<Error>,
 {
-    type Error = T::Error;
+    if let Some(from_byte) = T::FROM_BYTE {
+        return Ok(buf
+            .bytes_r
+            .read(len)?
+            .iter()
+            .map(|&b| from_byte(b))
+            .collect());
+    }
 
-    fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error>
-    where
-        Self: Sized,
-    {
-        let mut v = Vec::<T>::new();
-        if let Some(out) = <dyn Any>::downcast_mut::<Vec<u8>>(&mut v) {
-            let len = U56::decode(buf)?.0.try_into().unwrap();
+    let mut v = Vec::with_capacity(capacity_hint(buf, len));
+    for _ in 0..len {
+        v.push(T::decode(buf)?);
+    }
+    Ok(v)
+}
 
-            out.extend_from_slice(buf.bytes_r.read(len)?);
-        } else {
-            let len = usize::try_from(u32::decode(buf)?).unwrap();
+impl<T: Encodable> Encodable for Vec<T> {
+    fn encode(&self, out: &mut Writer) {
+        encode_seq(self, out)
+    }
+}
 
-            v.reserve_exact(len);
-            for _ in 0..len {
-                v.push(T::decode(buf)?);
-            }
-        }
+impl<T> Decodable for Vec<T>
+where
+    T: Decodable,
+    T::Error: From<Error>,
+{
+    type Error = T::Error;
 
-        Ok(v)
+    fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
+        let len = decode_seq_len::<T>(buf)?;
+        decode_vec(buf, len)
+    }
+
+    fn skip(buf: &mut Reader<'_>) -> Result<(), Self::Error> {
+        let len = decode_seq_len::<T>(buf)?;
+        skip_seq::<T>(buf, len)
     }
 }
 
@@ -242,50 +372,43 @@ impl<const LEN: usize> Decodable for [u8; LEN] {
     }
 }
 
-impl<T, const LEN: usize> Encodable for arrayvec::ArrayVec<T, LEN>
-where
-    T: Encodable + 'static,
-{
+impl<T: Encodable, const LEN: usize> Encodable for arrayvec::ArrayVec<T, LEN> {
     fn encode(&self, out: &mut Writer) {
-        if let Some(s) = <dyn Any>::downcast_ref::<arrayvec::ArrayVec<u8, LEN>>(self) {
-            s.as_slice().encode(out)
-        } else {
-            u32::try_from(self.len()).unwrap().encode(out);
-            for item in self {
-                item.encode(out)
-            }
-        }
+        encode_seq(self, out)
+    }
+}
+
+/// Reads the length of a sequence which may hold at most `cap` items.
+fn decode_bounded_seq_len<T: Decodable>(buf: &mut Reader<'_>, cap: usize) -> Result<usize, Error> {
+    let len = decode_seq_len::<T>(buf)?;
+    if len > cap {
+        return Err(Error::OverFlowError);
     }
+    Ok(len)
 }
 
 impl<T, const LEN: usize> Decodable for arrayvec::ArrayVec<T, LEN>
 where
-    T: Decodable + 'static,
+    T: Decodable,
     T::Error: From<Error>,
 {
     type Error = T::Error;
 
-    fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error>
-    where
-        Self: Sized,
-    {
-        let mut v = Self::new();
-        if let Some(out) = <dyn Any>::downcast_mut::<arrayvec::ArrayVec<u8, LEN>>(&mut v) {
-            let len = U56::decode(buf)?.0.try_into().unwrap();
-
-            out.try_extend_from_slice(buf.bytes_r.read(len)?)
-                .map_err(|_| Error::OverFlowError)?;
-        } else {
-            let len = usize::try_from(u32::decode(buf)?).unwrap();
+    fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
+        let len = decode_bounded_seq_len::<T>(buf, LEN)?;
 
-            for _ in 0..len {
-                v.try_push(T::decode(buf)?)
-                    .map_err(|_| Error::OverFlowError)?;
-            }
+        let mut v = Self::new();
+        for _ in 0..len {
+            v.push(T::decode(buf)?);
         }
 
         Ok(v)
     }
+
+    fn skip(buf: &mut Reader<'_>) -> Result<(), Self::Error> {
+        let len = decode_bounded_seq_len::<T>(buf, LEN)?;
+        skip_seq::<T>(buf, len)
+    }
 }
 
 impl Encodable for String {
@@ -300,6 +423,66 @@ impl Decodable for String {
     fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
         Ok(String::from_utf8(Decodable::decode(buf)?)?)
     }
+
+    fn skip(buf: &mut Reader<'_>) -> Result<(), Self::Error> {
+        core::str::from_utf8(read_bytes(buf)?)?;
+        Ok(())
+    }
+}
+
+macro_rules! impl_schema {
+    ($($ty:ty => $schema:expr,)*) => {
+        $(
+            impl CserSchema for $ty {
+                fn schema_type(_: &mut Definitions) -> Type {
+                    $schema
+                }
+            }
+        )*
+    };
+}
+
+impl_schema! {
+    u8 => Type::U8,
+    bool => Type::Bool,
+    u16 => Type::U16,
+    u32 => Type::U32,
+    u64 => Type::U64,
+    i64 => Type::I64,
+    U56 => Type::U56,
+    &[u8] => Type::Bytes,
+    Bytes => Type::Bytes,
+    String => Type::String,
+}
+
+impl<T: CserSchema> CserSchema for Option<T> {
+    fn schema_type(definitions: &mut Definitions) -> Type {
+        Type::Option(Box::new(T::schema_type(definitions)))
+    }
+}
+
+impl<T: CserSchema> CserSchema for Vec<T> {
+    fn schema_type(definitions: &mut Definitions) -> Type {
+        Type::Vec(Box::new(T::schema_type(definitions)))
+    }
+}
+
+impl<T: CserSchema, const LEN: usize> CserSchema for arrayvec::ArrayVec<T, LEN> {
+    fn schema_type(definitions: &mut Definitions) -> Type {
+        Type::Vec(Box::new(T::schema_type(definitions)))
+    }
+}
+
+impl<T: ?Sized> CserSchema for PhantomData<T> {
+    fn schema_type(_: &mut Definitions) -> Type {
+        Type::Tuple(vec![])
+    }
+}
+
+impl<const LEN: usize> CserSchema for [u8; LEN] {
+    fn schema_type(_: &mut Definitions) -> Type {
+        Type::FixedBytes(LEN)
+    }
 }
 
 #[macro_export]
@@ -317,15 +500,260 @@ macro_rules! impl_encodable_for_wrapper {
             fn decode(buf: &mut $crate::Reader<'_>) -> Result<Self, Self::Error> {
                 <$base>::decode(buf).map(<$wrapper>::from)
             }
+
+            fn skip(buf: &mut $crate::Reader<'_>) -> Result<(), Self::Error> {
+                <$base as $crate::Decodable>::skip(buf)
+            }
+        }
+
+        impl $crate::CserSchema for $wrapper {
+            fn schema_type(
+                definitions: &mut $crate::dynamic::Definitions,
+            ) -> $crate::dynamic::Type {
+                <$base as $crate::CserSchema>::schema_type(definitions)
+            }
         }
     };
 }
 
+/// Writes a big-endian unsigned integer as a byte string without leading zeros.
+#[cfg(any(feature = "ethnum", feature = "ethereum-types"))]
+fn encode_big_uint(be: &[u8], out: &mut Writer) {
+    let len = be.iter().take_while(|&&b| b == 0).count();
+    Encodable::encode(&&be[len..], out)
+}
+
+/// Reads an integer written by `encode_big_uint` into `N` big-endian bytes.
+#[cfg(any(feature = "ethnum", feature = "ethereum-types"))]
+fn decode_big_uint<const N: usize>(buf: &mut Reader<'_>) -> Result<[u8; N], Error> {
+    let offset = buf.bytes_r.position();
+    let data = arrayvec::ArrayVec::<u8, N>::decode(buf)?;
+    if data.first() == Some(&0) {
+        buf.tolerated_non_canonical(Violation::BigIntegerLeadingZero { offset })?;
+    }
+
+    let mut v = [0; N];
+    v[N - data.len()..].copy_from_slice(&data);
+    Ok(v)
+}
+
 #[cfg(feature = "ethereum-types")]
 mod ethereum_types_impl {
+    use super::*;
+
     impl_encodable_for_wrapper!(ethereum_types::Address, [u8; 20]);
     impl_encodable_for_wrapper!(ethereum_types::H256, [u8; 32]);
     impl_encodable_for_wrapper!(ethereum_types::H512, [u8; 64]);
+
+    impl Encodable for ethereum_types::U256 {
+        fn encode(&self, out: &mut Writer) {
+            encode_big_uint(&self.to_big_endian(), out)
+        }
+    }
+
+    impl Decodable for ethereum_types::U256 {
+        type Error = Error;
+
+        fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
+            decode_big_uint::<32>(buf).map(|v| Self::from_big_endian(&v))
+        }
+    }
+
+    // dynamic values hold big integers as `ethnum::U256`
+    #[cfg(feature = "ethnum")]
+    impl_schema! {
+        ethereum_types::U256 => Type::U256,
+    }
+}
+
+#[cfg(feature = "smallvec")]
+mod smallvec_impl {
+    use super::*;
+    use smallvec::{Array, SmallVec};
+
+    impl<A> Encodable for SmallVec<A>
+    where
+        A: Array,
+        A::Item: Encodable,
+    {
+        fn encode(&self, out: &mut Writer) {
+            encode_seq(self, out)
+        }
+    }
+
+    impl<A> Decodable for SmallVec<A>
+    where
+        A: Array,
+        A::Item: Decodable,
+        <A::Item as Decodable>::Error: From<Error>,
+    {
+        type Error = <A::Item as Decodable>::Error;
+
+        fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
+            let len = decode_seq_len::<A::Item>(buf)?;
+
+            let mut v = Self::with_capacity(capacity_hint(buf, len));
+            for _ in 0..len {
+                v.push(A::Item::decode(buf)?);
+            }
+
+            Ok(v)
+        }
+
+        fn skip(buf: &mut Reader<'_>) -> Result<(), Self::Error> {
+            let len = decode_seq_len::<A::Item>(buf)?;
+            skip_seq::<A::Item>(buf, len)
+        }
+    }
+
+    impl<A> CserSchema for SmallVec<A>
+    where
+        A: Array,
+        A::Item: CserSchema,
+    {
+        fn schema_type(definitions: &mut Definitions) -> Type {
+            Type::Vec(Box::new(A::Item::schema_type(definitions)))
+        }
+    }
+}
+
+#[cfg(feature = "heapless")]
+mod heapless_impl {
+    use super::*;
+    use heapless::{LenType, Vec};
+
+    impl<T: Encodable, LenT: LenType, const N: usize> Encodable for Vec<T, N, LenT> {
+        fn encode(&self, out: &mut Writer) {
+            encode_seq(self, out)
+        }
+    }
+
+    impl<T, LenT, const N: usize> Decodable for Vec<T, N, LenT>
+    where
+        T: Decodable,
+        T::Error: From<Error>,
+        LenT: LenType,
+    {
+        type Error = T::Error;
+
+        fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
+            let len = decode_bounded_seq_len::<T>(buf, N)?;
+
+            let mut v = Self::new();
+            for _ in 0..len {
+                v.push(T::decode(buf)?).map_err(|_| Error::OverFlowError)?;
+            }
+
+            Ok(v)
+        }
+
+        fn skip(buf: &mut Reader<'_>) -> Result<(), Self::Error> {
+            let len = decode_bounded_seq_len::<T>(buf, N)?;
+            skip_seq::<T>(buf, len)
+        }
+    }
+
+    impl<T: CserSchema, LenT: LenType, const N: usize> CserSchema for Vec<T, N, LenT> {
+        fn schema_type(definitions: &mut Definitions) -> Type {
+            Type::Vec(Box::new(T::schema_type(definitions)))
+        }
+    }
+}
+
+#[cfg(feature = "tinyvec")]
+mod tinyvec_impl {
+    use super::*;
+    use tinyvec::{Array, ArrayVec, TinyVec};
+
+    impl<A> Encodable for ArrayVec<A>
+    where
+        A: Array,
+        A::Item: Encodable,
+    {
+        fn encode(&self, out: &mut Writer) {
+            encode_seq(self, out)
+        }
+    }
+
+    impl<A> Decodable for ArrayVec<A>
+    where
+        A: Array,
+        A::Item: Decodable,
+        <A::Item as Decodable>::Error: From<Error>,
+    {
+        type Error = <A::Item as Decodable>::Error;
+
+        fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
+            let len = decode_bounded_seq_len::<A::Item>(buf, A::CAPACITY)?;
+
+            let mut v = Self::new();
+            for _ in 0..len {
+                v.push(A::Item::decode(buf)?);
+            }
+
+            Ok(v)
+        }
+
+        fn skip(buf: &mut Reader<'_>) -> Result<(), Self::Error> {
+            let len = decode_bounded_seq_len::<A::Item>(buf, A::CAPACITY)?;
+            skip_seq::<A::Item>(buf, len)
+        }
+    }
+
+    impl<A> CserSchema for ArrayVec<A>
+    where
+        A: Array,
+        A::Item: CserSchema,
+    {
+        fn schema_type(definitions: &mut Definitions) -> Type {
+            Type::Vec(Box::new(A::Item::schema_type(definitions)))
+        }
+    }
+
+    impl<A> Encodable for TinyVec<A>
+    where
+        A: Array,
+        A::Item: Encodable,
+    {
+        fn encode(&self, out: &mut Writer) {
+            encode_seq(self, out)
+        }
+    }
+
+    impl<A> Decodable for TinyVec<A>
+    where
+        A: Array,
+        A::Item: Decodable,
+        <A::Item as Decodable>::Error: From<Error>,
+    {
+        type Error = <A::Item as Decodable>::Error;
+
+        fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
+            let len = decode_seq_len::<A::Item>(buf)?;
+
+            let mut v = Self::with_capacity(capacity_hint(buf, len));
+            for _ in 0..len {
+                v.push(A::Item::decode(buf)?);
+            }
+
+            Ok(v)
+        }
+
+        fn skip(buf: &mut Reader<'_>) -> Result<(), Self::Error> {
+            let len = decode_seq_len::<A::Item>(buf)?;
+            skip_seq::<A::Item>(buf, len)
+        }
+    }
+
+    impl<A> CserSchema for TinyVec<A>
+    where
+        A: Array,
+        A::Item: CserSchema,
+    {
+        fn schema_type(definitions: &mut Definitions) -> Type {
+            Type::Vec(Box::new(A::Item::schema_type(definitions)))
+        }
+    }
 }
 
 #[cfg(feature = "ethnum")]
@@ -334,14 +762,14 @@ mod ethnum_impl {
 
     impl Encodable for ethnum::U256 {
         fn encode(&self, out: &mut Writer) {
-            let v = self.to_be_bytes();
-            let v = v.as_ref();
-            let v = &v[v.iter().take_while(|&&b| b == 0).count()..];
-
-            Encodable::encode(&v, out)
+            encode_big_uint(&self.to_be_bytes(), out)
         }
     }
 
+    impl_schema! {
+        ethnum::U256 => Type::U256,
+    }
+
     impl Decodable for ethnum::U256 {
         type Error = Error;
 
@@ -349,12 +777,7 @@ mod ethnum_impl {
         where
             Self: Sized,
         {
-            let data = arrayvec::ArrayVec::<u8, { (Self::BITS / 8) as usize }>::decode(buf)?;
-
-            let mut v = [0; (Self::BITS / 8) as usize];
-
-            v[(Self::BITS / 8) as usize - data.len()..].copy_from_slice(&data);
-            Ok(Self::from_be_bytes(v))
+            decode_big_uint(buf).map(Self::from_be_bytes)
         }
     }
 }
@@ -0,0 +1,280 @@
+//! Length-framed CSER records over blocking [`std::io`] streams.
+//!
+//! Each record is its CSER encoding prefixed with the length as a compact integer. Reading
+//! goes byte by byte through the length prefix, so wrap unbuffered sources in a `BufReader`.
+
+use super::{
+    binary::deserialize,
+    error::Error,
+    fast,
+    read_writer::{read_uint64_compact, write_uint64_compact},
+    Decodable, Encodable, Writer,
+};
+use std::{
+    convert::Infallible,
+    io::{self, Read, Write},
+    marker::PhantomData,
+};
+
+/// Longest compact encoding of a `u64`.
+pub(crate) const MAX_LEN_PREFIX: usize = 10;
+
+pub const DEFAULT_MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CodecError<E> {
+    #[error("frame of {0} bytes exceeds the limit")]
+    FrameTooLarge(u64),
+    #[error("invalid frame length: {0}")]
+    Length(Error),
+    #[error("stream ended in the middle of a frame")]
+    Truncated,
+    #[error("failed to decode frame")]
+    Decode(E),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+pub(crate) fn frame_len_prefix(frame_len: usize) -> Vec<u8> {
+    let mut w = fast::Writer::new(Vec::with_capacity(MAX_LEN_PREFIX));
+    write_uint64_compact(&mut w, frame_len as u64);
+    w.buf
+}
+
+pub(crate) fn parse_frame_len<E>(
+    prefix: &[u8],
+    max_frame_len: usize,
+) -> Result<usize, CodecError<E>> {
+    let frame_len =
+        read_uint64_compact(&mut fast::Reader::new(prefix)).map_err(CodecError::Length)?;
+    match usize::try_from(frame_len) {
+        Ok(v) if v <= max_frame_len => Ok(v),
+        _ => Err(CodecError::FrameTooLarge(frame_len)),
+    }
+}
+
+/// Writes `value` as a single frame.
+pub fn write_framed(
+    w: &mut impl Write,
+    value: &(impl Encodable + ?Sized),
+) -> Result<(), CodecError<Infallible>> {
+    write_framed_with_limit(w, value, DEFAULT_MAX_FRAME_LEN)
+}
+
+/// Same as [`write_framed`], refusing to write frames longer than `max_frame_len`.
+pub fn write_framed_with_limit(
+    w: &mut impl Write,
+    value: &(impl Encodable + ?Sized),
+    max_frame_len: usize,
+) -> Result<(), CodecError<Infallible>> {
+    let mut out = Writer::new();
+    value.encode(&mut out);
+    let frame = out.output();
+
+    if frame.len() > max_frame_len {
+        return Err(CodecError::FrameTooLarge(frame.len() as u64));
+    }
+
+    w.write_all(&frame_len_prefix(frame.len()))?;
+    w.write_all(&frame)?;
+    Ok(())
+}
+
+/// Reads a single frame. Returns `None` if the stream ends before the frame starts.
+pub fn read_framed<T>(r: &mut impl Read) -> Result<Option<T>, CodecError<T::Error>>
+where
+    T: Decodable,
+    T::Error: From<Error>,
+{
+    read_framed_with_limit(r, DEFAULT_MAX_FRAME_LEN)
+}
+
+/// Same as [`read_framed`], rejecting frames longer than `max_frame_len`.
+pub fn read_framed_with_limit<T>(
+    r: &mut impl Read,
+    max_frame_len: usize,
+) -> Result<Option<T>, CodecError<T::Error>>
+where
+    T: Decodable,
+    T::Error: From<Error>,
+{
+    let mut prefix = Vec::with_capacity(MAX_LEN_PREFIX);
+    loop {
+        let mut b = 0;
+        let n = match r.read(std::slice::from_mut(&mut b)) {
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            res => res?,
+        };
+        if n == 0 {
+            if prefix.is_empty() {
+                return Ok(None);
+            }
+            return Err(CodecError::Truncated);
+        }
+        prefix.push(b);
+
+        // the last byte of the length carries the stop flag
+        if b & 0b10000000 != 0 {
+            break;
+        }
+        if prefix.len() == MAX_LEN_PREFIX {
+            return Err(CodecError::Length(Error::MalformedEncoding));
+        }
+    }
+    let frame_len = parse_frame_len(&prefix, max_frame_len)?;
+
+    // do not trust the length with an allocation before the data arrives
+    let mut frame = Vec::new();
+    r.take(frame_len as u64).read_to_end(&mut frame)?;
+    if frame.len() < frame_len {
+        return Err(CodecError::Truncated);
+    }
+
+    deserialize(&frame).map(Some).map_err(CodecError::Decode)
+}
+
+/// Iterator over the frames of a stream, until its end or the first error.
+pub struct FramedReader<R, T> {
+    inner: R,
+    max_frame_len: usize,
+    done: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<R, T> FramedReader<R, T> {
+    pub fn new(inner: R) -> Self {
+        Self::with_max_frame_len(inner, DEFAULT_MAX_FRAME_LEN)
+    }
+
+    pub fn with_max_frame_len(inner: R, max_frame_len: usize) -> Self {
+        Self {
+            inner,
+            max_frame_len,
+            done: false,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R, T> Iterator for FramedReader<R, T>
+where
+    R: Read,
+    T: Decodable,
+    T::Error: From<Error>,
+{
+    type Item = Result<T, CodecError<T::Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let res = read_framed_with_limit(&mut self.inner, self.max_frame_len).transpose();
+        if !matches!(res, Some(Ok(_))) {
+            self.done = true;
+        }
+        res
+    }
+}
+
+impl<R, T> std::iter::FusedIterator for FramedReader<R, T>
+where
+    R: Read,
+    T: Decodable,
+    T::Error: From<Error>,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(items: &[Vec<u64>]) -> Vec<u8> {
+        let mut buf = vec![];
+        for item in items {
+            write_framed(&mut buf, item).unwrap();
+        }
+        buf
+    }
+
+    #[test]
+    fn roundtrip() {
+        let items = vec![vec![], vec![1, 2, 3], vec![u64::MAX; 100]];
+        let buf = stream(&items);
+
+        let decoded = FramedReader::<_, Vec<u64>>::new(buf.as_slice())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(decoded, items);
+    }
+
+    #[test]
+    fn truncated() {
+        let items = vec![vec![1, 2, 3], vec![4, 5, 6]];
+        let buf = stream(&items);
+
+        // cut right after the length and inside the frame
+        for cut in [stream(&items[..1]).len() + 1, buf.len() - 1] {
+            let mut r = FramedReader::<_, Vec<u64>>::new(&buf[..cut]);
+            assert_eq!(r.next().unwrap().unwrap(), items[0]);
+            assert!(matches!(r.next(), Some(Err(CodecError::Truncated))));
+            assert!(r.next().is_none());
+        }
+    }
+
+    #[test]
+    fn limits() {
+        let buf = stream(&[vec![1, 2, 3]]);
+
+        assert!(matches!(
+            read_framed_with_limit::<Vec<u64>>(&mut buf.as_slice(), 4),
+            Err(CodecError::FrameTooLarge(_))
+        ));
+        assert!(matches!(
+            read_framed::<Vec<u64>>(&mut [0_u8; MAX_LEN_PREFIX].as_slice()),
+            Err(CodecError::Length(Error::MalformedEncoding))
+        ));
+
+        let mut out = vec![];
+        assert!(matches!(
+            write_framed_with_limit(&mut out, &vec![1_u64, 2, 3], 4),
+            Err(CodecError::FrameTooLarge(_))
+        ));
+        assert!(out.is_empty());
+    }
+
+    /// Fails every other read with [`io::ErrorKind::Interrupted`].
+    struct Interrupting<'a> {
+        inner: &'a [u8],
+        interrupt: bool,
+    }
+
+    impl Read for Interrupting<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.interrupt = !self.interrupt;
+            if self.interrupt {
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            self.inner.read(buf)
+        }
+    }
+
+    #[test]
+    fn interrupted() {
+        let items = vec![vec![1, 2, 3], vec![u64::MAX; 100]];
+        let buf = stream(&items);
+
+        let r = Interrupting {
+            inner: &buf,
+            interrupt: false,
+        };
+        let decoded = FramedReader::<_, Vec<u64>>::new(r)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(decoded, items);
+    }
+}
@@ -0,0 +1,276 @@
+//! Human-readable JSON views of CSER encodings, driven by a [`Schema`].
+//!
+//! Integers are JSON numbers while they fit into the 53 bits a double represents exactly,
+//! `0x` prefixed hex strings beyond that. Byte strings are `0x` prefixed hex, `u256` a
+//! decimal string, structs objects and enum values `{"Variant": fields}`, or just
+//! `"Variant"` for unit variants.
+
+use super::{
+    dynamic::{decode_with_schema, encode_with_schema, Definition, Fields, Schema, Type, Value},
+    CserSchema,
+};
+use anyhow::{bail, format_err, Context};
+use serde_json::{Map, Value as Json};
+
+/// Largest integer a JSON number carries without loss of precision.
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// Renders a blob holding a `T` as JSON.
+pub fn to_json<T: CserSchema>(input: &[u8]) -> anyhow::Result<Json> {
+    to_json_with_schema(&T::schema(), input)
+}
+
+/// Encodes the JSON view of a `T` into a blob.
+pub fn from_json<T: CserSchema>(v: &Json) -> anyhow::Result<Vec<u8>> {
+    from_json_with_schema(&T::schema(), v)
+}
+
+/// Renders a blob as JSON, decoding it with `schema` instead of a static type.
+pub fn to_json_with_schema(schema: &Schema, input: &[u8]) -> anyhow::Result<Json> {
+    Ok(value_to_json(&decode_with_schema(schema, input)?))
+}
+
+/// Encodes a JSON view into a blob, reading it with `schema` instead of a static type.
+pub fn from_json_with_schema(schema: &Schema, v: &Json) -> anyhow::Result<Vec<u8>> {
+    encode_with_schema(schema, &value_from_json(schema, &schema.root, v)?)
+}
+
+fn hex(b: &[u8]) -> Json {
+    let mut s = String::with_capacity(2 + b.len() * 2);
+    s.push_str("0x");
+    for byte in b {
+        s.push_str(&format!("{byte:02x}"));
+    }
+    s.into()
+}
+
+/// Renders a decoded value as JSON, the inverse of [`value_from_json`].
+pub fn value_to_json(v: &Value) -> Json {
+    match v {
+        Value::Bool(v) => (*v).into(),
+        Value::Uint(v) if *v <= MAX_SAFE_INTEGER => (*v).into(),
+        Value::Uint(v) => format!("{v:#x}").into(),
+        Value::Int(v) if v.unsigned_abs() <= MAX_SAFE_INTEGER => (*v).into(),
+        Value::Int(v) if *v < 0 => format!("-{:#x}", v.unsigned_abs()).into(),
+        Value::Int(v) => format!("{v:#x}").into(),
+        #[cfg(feature = "ethnum")]
+        Value::U256(v) => v.to_string().into(),
+        Value::Bytes(v) => hex(v),
+        Value::String(v) => v.clone().into(),
+        Value::Option(v) => v.as_deref().map_or(Json::Null, value_to_json),
+        Value::Seq(items) => items.iter().map(value_to_json).collect(),
+        Value::Struct(fields) => fields
+            .iter()
+            .map(|(name, v)| (name.clone(), value_to_json(v)))
+            .collect::<Map<_, _>>()
+            .into(),
+        Value::Variant(name, v) if **v == Value::Seq(vec![]) => name.clone().into(),
+        Value::Variant(name, v) => [(name.clone(), value_to_json(v))]
+            .into_iter()
+            .collect::<Map<_, _>>()
+            .into(),
+    }
+}
+
+/// Digits after the `0x` prefix. `from_str_radix` alone would also take a sign.
+fn hex_digits(s: &str) -> anyhow::Result<&str> {
+    let digits = s.strip_prefix("0x").context("expected 0x prefixed hex")?;
+    if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        bail!("invalid hex string {s}");
+    }
+    Ok(digits)
+}
+
+fn uint(v: &Json) -> anyhow::Result<u64> {
+    match v {
+        Json::String(s) => Ok(u64::from_str_radix(hex_digits(s)?, 16)?),
+        v => v.as_u64().context("expected unsigned integer"),
+    }
+}
+
+fn int(v: &Json) -> anyhow::Result<i64> {
+    match v {
+        Json::String(s) => {
+            let (neg, abs) = match s.strip_prefix('-') {
+                Some(abs) => (true, abs),
+                None => (false, s.as_str()),
+            };
+            let abs = uint(&Json::String(abs.to_string()))?;
+            if neg {
+                0_i64.checked_sub_unsigned(abs)
+            } else {
+                i64::try_from(abs).ok()
+            }
+            .ok_or_else(|| format_err!("{s} is out of range"))
+        }
+        v => v.as_i64().context("expected integer"),
+    }
+}
+
+fn bytes(v: &Json) -> anyhow::Result<Vec<u8>> {
+    let s = v.as_str().context("expected hex string")?;
+    let digits = hex_digits(s)?;
+    if digits.len() % 2 != 0 {
+        bail!("invalid hex string {s}");
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| Ok(u8::from_str_radix(&digits[i..i + 2], 16)?))
+        .collect()
+}
+
+fn array(v: &Json) -> anyhow::Result<&Vec<Json>> {
+    v.as_array().context("expected array")
+}
+
+/// Reads the JSON view of a value of type `ty` back, using `schema` for named types.
+pub fn value_from_json(schema: &Schema, ty: &Type, v: &Json) -> anyhow::Result<Value> {
+    Ok(match ty {
+        Type::Bool => Value::Bool(v.as_bool().context("expected bool")?),
+        Type::U8 | Type::U16 | Type::U32 | Type::U64 | Type::U56 => Value::Uint(uint(v)?),
+        Type::I64 => Value::Int(int(v)?),
+        #[cfg(feature = "ethnum")]
+        Type::U256 => Value::U256(
+            v.as_str()
+                .context("expected decimal string")?
+                .parse::<ethnum::U256>()?,
+        ),
+        Type::Bytes | Type::FixedBytes(_) => Value::Bytes(bytes(v)?),
+        Type::String => Value::String(v.as_str().context("expected string")?.to_string()),
+        Type::Vec(inner) if **inner == Type::U8 => Value::Bytes(bytes(v)?),
+        Type::Vec(inner) => Value::Seq(
+            array(v)?
+                .iter()
+                .map(|item| value_from_json(schema, inner, item))
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        Type::Option(inner) => Value::Option(if v.is_null() {
+            None
+        } else {
+            Some(Box::new(value_from_json(schema, inner, v)?))
+        }),
+        Type::Tuple(types) => seq(schema, types, v)?,
+        Type::Named(name) => match schema.definitions.get(name) {
+            Some(Definition::Struct(fields)) => fields_from_json(schema, fields, v)?,
+            Some(Definition::Enum(variants)) => {
+                let (variant, fields) = match v {
+                    Json::String(variant) => (variant, &Json::Null),
+                    Json::Object(map) if map.len() == 1 => map.iter().next().unwrap(),
+                    _ => bail!("expected a variant of `{name}`"),
+                };
+                let (_, def) = variants
+                    .iter()
+                    .find(|(name, _)| name == variant)
+                    .ok_or_else(|| format_err!("`{name}` has no variant `{variant}`"))?;
+                Value::Variant(
+                    variant.clone(),
+                    Box::new(fields_from_json(schema, def, fields)?),
+                )
+            }
+            None => bail!("unknown type `{name}`"),
+        },
+    })
+}
+
+fn seq(schema: &Schema, types: &[Type], v: &Json) -> anyhow::Result<Value> {
+    let items = array(v)?;
+    if items.len() != types.len() {
+        bail!("expected {} items, got {}", types.len(), items.len());
+    }
+    Ok(Value::Seq(
+        types
+            .iter()
+            .zip(items)
+            .map(|(ty, item)| value_from_json(schema, ty, item))
+            .collect::<anyhow::Result<_>>()?,
+    ))
+}
+
+fn fields_from_json(schema: &Schema, fields: &Fields, v: &Json) -> anyhow::Result<Value> {
+    Ok(match fields {
+        Fields::Unit => Value::Seq(vec![]),
+        Fields::Tuple(types) => seq(schema, types, v)?,
+        Fields::Named(fields) => {
+            let map = v.as_object().context("expected object")?;
+            Value::Struct(
+                fields
+                    .iter()
+                    .map(|(name, ty)| {
+                        let item = map
+                            .get(name)
+                            .ok_or_else(|| format_err!("missing field `{name}`"))?;
+                        Ok((name.clone(), value_from_json(schema, ty, item)?))
+                    })
+                    .collect::<anyhow::Result<_>>()?,
+            )
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn roundtrip() {
+        let schema = "
+            struct Point { x: i64, y: i64 }
+            enum Shape { Empty, Circle(Point, u32), Line { from: Point, to: Point } }
+            (vec<Shape>, option<u64>, [u8; 2], vec<u8>)
+        "
+        .parse::<Schema>()
+        .unwrap();
+        let v = json!([
+            [
+                "Empty",
+                {"Circle": [{"x": 1, "y": -1}, 5]},
+                {"Line": {"from": {"x": 9007199254740991_i64, "y": "-0x20000000000000"}, "to": {"x": "0x7fffffffffffffff", "y": 4}}},
+            ],
+            "0xffffffffffffffff",
+            "0xabcd",
+            "0x",
+        ]);
+
+        let blob = from_json_with_schema(&schema, &v).unwrap();
+        assert_eq!(to_json_with_schema(&schema, &blob).unwrap(), v);
+    }
+
+    #[test]
+    fn typed() {
+        let v = vec![None, Some(u64::MAX)];
+        let mut w = crate::Writer::new();
+        crate::Encodable::encode(&v, &mut w);
+        let blob = w.output();
+
+        let json = to_json::<Vec<Option<u64>>>(&blob).unwrap();
+        assert_eq!(json, json!([null, "0xffffffffffffffff"]));
+        assert_eq!(from_json::<Vec<Option<u64>>>(&json).unwrap(), blob);
+        assert!(to_json::<Vec<u32>>(&blob).is_err());
+    }
+
+    #[test]
+    fn integers() {
+        for (v, json) in [
+            (Value::Uint(MAX_SAFE_INTEGER), json!(9007199254740991_u64)),
+            (Value::Uint(MAX_SAFE_INTEGER + 1), json!("0x20000000000000")),
+            (
+                Value::Int(-(MAX_SAFE_INTEGER as i64)),
+                json!(-9007199254740991_i64),
+            ),
+            (Value::Int(i64::MIN), json!("-0x8000000000000000")),
+        ] {
+            assert_eq!(value_to_json(&v), json);
+        }
+
+        let schema = Schema::new(Type::I64);
+        assert!(value_from_json(&schema, &Type::I64, &json!("0x8000000000000000")).is_err());
+        assert!(value_from_json(&schema, &Type::U8, &json!("12")).is_err());
+        assert!(value_from_json(&schema, &Type::Bytes, &json!("0x123")).is_err());
+        for signed in ["0x+1", "-0x+1", "0x-1"] {
+            assert!(value_from_json(&schema, &Type::U64, &json!(signed)).is_err());
+            assert!(value_from_json(&schema, &Type::I64, &json!(signed)).is_err());
+        }
+        assert!(value_from_json(&schema, &Type::Bytes, &json!("0x+1")).is_err());
+    }
+}
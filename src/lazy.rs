@@ -0,0 +1,128 @@
+use super::{
+    binary::binary_to_cser,
+    dynamic::{Definitions, Type},
+    error::Error,
+    CserSchema, Decodable, Reader,
+};
+use core::{fmt, marker::PhantomData};
+
+/// Field which is skipped while decoding the containing value and only remembers where
+/// it starts, so that it can be decoded later out of the same input.
+pub struct LazyField<T> {
+    bits: usize,
+    bytes: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> LazyField<T>
+where
+    T: Decodable,
+    T::Error: From<Error>,
+{
+    /// Decodes the field out of `input`, the whole encoding the containing value was
+    /// decoded from.
+    pub fn decode_from(&self, input: &[u8]) -> Result<T, T::Error> {
+        let (bbits, bbytes, _) = binary_to_cser(input)?;
+
+        let mut r = Reader::new(bbits, bbytes);
+        r.bits_r.seek(self.bits)?;
+        r.bytes_r.seek(self.bytes)?;
+        T::decode(&mut r)
+    }
+}
+
+impl<T> Decodable for LazyField<T>
+where
+    T: Decodable,
+{
+    type Error = T::Error;
+
+    fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
+        let this = Self {
+            bits: buf.bits_r.position(),
+            bytes: buf.bytes_r.position(),
+            _marker: PhantomData,
+        };
+        T::skip(buf)?;
+        Ok(this)
+    }
+
+    fn skip(buf: &mut Reader<'_>) -> Result<(), Self::Error> {
+        T::skip(buf)
+    }
+}
+
+impl<T: CserSchema> CserSchema for LazyField<T> {
+    fn schema_type(definitions: &mut Definitions) -> Type {
+        T::schema_type(definitions)
+    }
+}
+
+impl<T> Clone for LazyField<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for LazyField<T> {}
+
+impl<T> PartialEq for LazyField<T> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.bits, self.bytes) == (other.bits, other.bytes)
+    }
+}
+
+impl<T> Eq for LazyField<T> {}
+
+impl<T> fmt::Debug for LazyField<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyField")
+            .field("bits", &self.bits)
+            .field("bytes", &self.bytes)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{binary::deserialize_cb_with, error::Strictness, Encodable, Writer};
+
+    #[test]
+    fn decode_later() {
+        let mut w = Writer::new();
+        7_u32.encode(&mut w);
+        vec![Some(1_u64), None, Some(u64::MAX)].encode(&mut w);
+        "payload".to_string().encode(&mut w);
+        true.encode(&mut w);
+        let input = w.output();
+
+        let ((a, lazy, s, b), _) =
+            deserialize_cb_with::<_, anyhow::Error>(&input, Strictness::Strict, |r| {
+                Ok((
+                    u32::decode(r)?,
+                    LazyField::<Vec<Option<u64>>>::decode(r)?,
+                    LazyField::<String>::decode(r)?,
+                    bool::decode(r)?,
+                ))
+            })
+            .unwrap();
+
+        assert_eq!((a, b), (7, true));
+        assert_eq!(
+            lazy.decode_from(&input).unwrap(),
+            [Some(1), None, Some(u64::MAX)]
+        );
+        assert_eq!(s.decode_from(&input).unwrap(), "payload");
+    }
+
+    #[test]
+    fn skip_rejects_invalid() {
+        let mut w = Writer::new();
+        [0xff_u8, 0xfe].as_slice().encode(&mut w);
+        let input = w.output();
+
+        assert!(deserialize_cb_with(&input, Strictness::Strict, Vec::<u8>::skip).is_ok());
+        assert!(deserialize_cb_with(&input, Strictness::Strict, String::skip).is_err());
+    }
+}
@@ -1,15 +1,40 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 mod binary;
 mod bits;
+mod bounded;
+#[cfg(feature = "tokio-util")]
+pub mod codec;
+pub mod dynamic;
+mod embedded;
 mod error;
 mod fast;
+#[cfg(feature = "digest")]
+pub mod hash;
 mod imp;
+#[cfg(feature = "std")]
+pub mod io;
+#[cfg(feature = "serde_json")]
+pub mod json;
+mod lazy;
+#[cfg(feature = "opera")]
+pub mod opera;
 mod read_writer;
+#[cfg(feature = "proptest")]
+pub mod testing;
 
 pub use self::{
-    binary::deserialize,
+    binary::{
+        check_canonical, deserialize, deserialize_cb_with, deserialize_with, sections, Sections,
+    },
     bits::{Reader as BitsReader, Writer as BitsWriter},
-    error::Error,
-    read_writer::{Reader, Writer},
+    bounded::{BoundedBytes, BoundedDecodable, BoundedVec},
+    embedded::Embedded,
+    error::{Error, Strictness, Violation},
+    lazy::LazyField,
+    read_writer::{Reader, ReaderCheckpoint, Writer, WriterCheckpoint},
 };
 use auto_impl::auto_impl;
 #[cfg(feature = "derive")]
@@ -18,23 +43,64 @@ use derive_more::Deref;
 
 #[auto_impl(&, Box, Arc)]
 pub trait Encodable {
+    /// Whether sequences of the type are written as byte strings, a [`U56`] length
+    /// followed by the items, instead of a `u32` count followed by the items. Only set by
+    /// `u8` and wrappers of it, whose items are single bytes in the bytes section. Decoding
+    /// goes by [`Decodable::FROM_BYTE`], which must be set for the same types.
+    const IS_BYTE: bool = false;
+
     fn encode(&self, out: &mut Writer);
 }
 
 pub trait Decodable: Sized {
     type Error;
 
+    /// Set for the types whose [`Encodable::IS_BYTE`] is set, turning one byte of a byte
+    /// string into an item. Byte strings are decoded in one go through it.
+    const FROM_BYTE: Option<fn(u8) -> Self> = None;
+
     fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error>;
+
+    /// Advances past an encoded value, accepting exactly what [`Decodable::decode`] accepts.
+    /// Types which allocate on decoding override it to avoid that.
+    fn skip(buf: &mut Reader<'_>) -> Result<(), Self::Error> {
+        Self::decode(buf).map(drop)
+    }
+}
+
+/// Values stored directly in the bits section by fields marked `#[cser(bits = N)]`.
+pub trait BitPacked: Sized {
+    fn to_bits(&self) -> u64;
+
+    /// `None` if `v` is out of the range of the type.
+    fn from_bits(v: u64) -> Option<Self>;
+}
+
+/// Static description of the wire layout of a type, for [`dynamic`] decoding and
+/// documentation.
+pub trait CserSchema {
+    /// Type of the encoding, adding the structs it refers to to `definitions`.
+    fn schema_type(definitions: &mut dynamic::Definitions) -> dynamic::Type;
+
+    fn schema() -> dynamic::Schema {
+        let mut definitions = dynamic::Definitions::new();
+        let root = Self::schema_type(&mut definitions);
+        dynamic::Schema { definitions, root }.with_short_names()
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deref)]
 pub struct U56(u64);
 
+impl U56 {
+    pub const MAX: Self = Self(0x00ff_ffff_ffff_ffff);
+}
+
 impl TryFrom<u64> for U56 {
     type Error = ();
 
     fn try_from(value: u64) -> Result<Self, Self::Error> {
-        if value > 0x00ff_ffff_ffff_ffff {
+        if value > Self::MAX.0 {
             Err(())
         } else {
             Ok(Self(value))
@@ -0,0 +1,875 @@
+//! Go-opera consensus types: `idx` indices, `hash.Event` and the `inter` event layout.
+
+use super::{error::Error, Decodable, Encodable, Reader, Writer};
+use alloc::vec::Vec;
+use bytes::Bytes;
+use derive_more::{Deref, From};
+use ethereum_types::{Address, H256, H512, U256};
+
+macro_rules! index {
+    ($(#[$meta:meta])* $name:ident($base:ty)) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From)]
+        pub struct $name(pub $base);
+
+        crate::impl_encodable_for_wrapper!($name, $base);
+    };
+}
+
+index!(
+    /// `idx.Epoch`
+    Epoch(u32)
+);
+index!(
+    /// `idx.Lamport`
+    Lamport(u32)
+);
+index!(
+    /// `idx.Frame`
+    Frame(u32)
+);
+index!(
+    /// `idx.Event`, sequence number of an event of its creator
+    Seq(u32)
+);
+index!(
+    /// `idx.ValidatorID`
+    ValidatorId(u32)
+);
+index!(
+    /// `idx.Block`
+    Block(u64)
+);
+index!(
+    /// `inter.Timestamp`, nanoseconds since Unix epoch
+    Timestamp(u64)
+);
+
+/// `hash.Event`: the epoch and Lamport time of the event followed by 24 bytes of its hash.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deref, From)]
+pub struct EventHash(pub H256);
+
+crate::impl_encodable_for_wrapper!(EventHash, H256);
+
+impl EventHash {
+    pub fn epoch(&self) -> Epoch {
+        Epoch(u32::from_be_bytes(self.0[..4].try_into().unwrap()))
+    }
+
+    pub fn lamport(&self) -> Lamport {
+        Lamport(u32::from_be_bytes(self.0[4..8].try_into().unwrap()))
+    }
+}
+
+/// `inter.GasPowerLeft`, gas power left in the short and long windows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct GasPowerLeft {
+    pub gas: [u64; 2],
+}
+
+impl Encodable for GasPowerLeft {
+    fn encode(&self, out: &mut Writer) {
+        for gas in &self.gas {
+            gas.encode(out);
+        }
+    }
+}
+
+impl Decodable for GasPowerLeft {
+    type Error = Error;
+
+    fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            gas: [u64::decode(buf)?, u64::decode(buf)?],
+        })
+    }
+}
+
+/// `inter.Event`, the event header.
+///
+/// Version 0 events carry only the transactions flag. Later versions are marked by two zero
+/// bits and the version byte ahead of the header, and add the network fork ID and the flags
+/// of the LLR payload parts. Those two bits are the size bits of the epoch in version 0, so
+/// a version 0 event must have an epoch of at least 256 to be told apart.
+///
+/// Parents are stored without their epoch and with their Lamport time as a difference to
+/// the event's one, so they must be from the event's epoch and not have a higher Lamport
+/// time. Decoding rejects differences which would take the parent's Lamport time below
+/// zero.
+///
+/// # Panics
+///
+/// Encoding an event which breaks these rules panics, go-opera refuses to encode those as
+/// well. [`Event::try_encode`] returns an error instead.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Event {
+    pub version: u8,
+    pub net_fork_id: u16,
+    pub epoch: Epoch,
+    pub seq: Seq,
+    pub frame: Frame,
+    pub creator: ValidatorId,
+    pub lamport: Lamport,
+    pub creation_time: Timestamp,
+    pub median_time: Timestamp,
+    pub gas_power_used: u64,
+    pub gas_power_left: GasPowerLeft,
+    /// Parents of the event, all of them from the same epoch and with lower Lamport time.
+    pub parents: Vec<EventHash>,
+    pub prev_epoch_hash: Option<H256>,
+    pub any_txs: bool,
+    pub any_misbehaviour_proofs: bool,
+    pub any_epoch_vote: bool,
+    pub any_block_votes: bool,
+    /// Only stored when the event has a payload, decoded as zero otherwise.
+    pub payload_hash: H256,
+    pub extra: Bytes,
+}
+
+impl Event {
+    pub fn any_payload(&self) -> bool {
+        self.any_txs || self.any_misbehaviour_proofs || self.any_epoch_vote || self.any_block_votes
+    }
+
+    /// Checks the rules the encoding relies on, see [`Event`].
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.version == 0 && *self.epoch < 256 {
+            return Err(Error::Custom(
+                "version 0 events need an epoch of at least 256",
+            ));
+        }
+        for parent in &self.parents {
+            if parent.epoch() != self.epoch {
+                return Err(Error::Custom("parents must be from the event's epoch"));
+            }
+            if parent.lamport() > self.lamport {
+                return Err(Error::Custom(
+                    "parents must not have a higher Lamport time than the event",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Encodes the event, or returns the error of [`Event::validate`] without writing
+    /// anything.
+    pub fn try_encode(&self, out: &mut Writer) -> Result<(), Error> {
+        self.validate()?;
+        self.encode(out);
+        Ok(())
+    }
+}
+
+impl Encodable for Event {
+    fn encode(&self, out: &mut Writer) {
+        if let Err(Error::Custom(msg)) = self.validate() {
+            panic!("{msg}");
+        }
+        if self.version > 0 {
+            out.bits_w.write(2, 0);
+            self.version.encode(out);
+            self.net_fork_id.encode(out);
+        }
+
+        self.epoch.encode(out);
+        self.lamport.encode(out);
+        self.creator.encode(out);
+        self.seq.encode(out);
+        self.frame.encode(out);
+        self.creation_time.encode(out);
+        // median time is stored as a difference to the creation time
+        (*self.creation_time as i64)
+            .wrapping_sub(*self.median_time as i64)
+            .encode(out);
+
+        self.gas_power_used.encode(out);
+        self.gas_power_left.encode(out);
+
+        u32::try_from(self.parents.len()).unwrap().encode(out);
+        for parent in &self.parents {
+            // epoch is the same as the event's one, Lamport time is stored as a difference
+            (*self.lamport - *parent.lamport()).encode(out);
+            out.bytes_w.write(&parent.0[8..]);
+        }
+
+        self.prev_epoch_hash.encode(out);
+
+        self.any_txs.encode(out);
+        if self.version > 0 {
+            self.any_misbehaviour_proofs.encode(out);
+            self.any_epoch_vote.encode(out);
+            self.any_block_votes.encode(out);
+        }
+        if self.any_payload() {
+            self.payload_hash.encode(out);
+        }
+
+        (&*self.extra).encode(out);
+    }
+}
+
+impl Decodable for Event {
+    type Error = Error;
+
+    fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
+        let mut version = 0;
+        let mut net_fork_id = 0;
+        if buf.bits_r.view(2)? == 0 {
+            buf.bits_r.read(2)?;
+            version = u8::decode(buf)?;
+            if version == 0 {
+                return Err(Error::NonCanonicalEncoding);
+            }
+            net_fork_id = u16::decode(buf)?;
+        }
+
+        let epoch = Epoch::decode(buf)?;
+        let lamport = Lamport::decode(buf)?;
+        let creator = ValidatorId::decode(buf)?;
+        let seq = Seq::decode(buf)?;
+        let frame = Frame::decode(buf)?;
+        let creation_time = Timestamp::decode(buf)?;
+        let median_time = Timestamp((*creation_time as i64).wrapping_sub(i64::decode(buf)?) as u64);
+
+        let gas_power_used = u64::decode(buf)?;
+        let gas_power_left = GasPowerLeft::decode(buf)?;
+
+        let parents_len = usize::try_from(u32::decode(buf)?).unwrap();
+        let mut parents = Vec::with_capacity(parents_len.min(buf.bytes_r.remaining() / 24));
+        for _ in 0..parents_len {
+            let parent_lamport = lamport
+                .checked_sub(u32::decode(buf)?)
+                .ok_or(Error::MalformedEncoding)?;
+
+            let mut parent = H256::zero();
+            parent[..4].copy_from_slice(&epoch.to_be_bytes());
+            parent[4..8].copy_from_slice(&parent_lamport.to_be_bytes());
+            parent[8..].copy_from_slice(buf.bytes_r.read(24)?);
+            parents.push(EventHash(parent));
+        }
+
+        let prev_epoch_hash = Option::<H256>::decode(buf)?;
+
+        let any_txs = bool::decode(buf)?;
+        let (mut any_misbehaviour_proofs, mut any_epoch_vote, mut any_block_votes) =
+            Default::default();
+        if version > 0 {
+            any_misbehaviour_proofs = bool::decode(buf)?;
+            any_epoch_vote = bool::decode(buf)?;
+            any_block_votes = bool::decode(buf)?;
+        }
+
+        let mut this = Self {
+            version,
+            net_fork_id,
+            epoch,
+            seq,
+            frame,
+            creator,
+            lamport,
+            creation_time,
+            median_time,
+            gas_power_used,
+            gas_power_left,
+            parents,
+            prev_epoch_hash,
+            any_txs,
+            any_misbehaviour_proofs,
+            any_epoch_vote,
+            any_block_votes,
+            payload_hash: H256::zero(),
+            extra: Bytes::new(),
+        };
+        if this.any_payload() {
+            this.payload_hash = H256::decode(buf)?;
+        }
+        this.extra = Bytes::decode(buf)?;
+
+        Ok(this)
+    }
+}
+
+/// `inter.LlrEpochVote`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EpochVote {
+    pub epoch: Epoch,
+    pub vote: H256,
+}
+
+impl Encodable for EpochVote {
+    fn encode(&self, out: &mut Writer) {
+        self.epoch.encode(out);
+        self.vote.encode(out);
+    }
+}
+
+impl Decodable for EpochVote {
+    type Error = Error;
+
+    fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            epoch: Epoch::decode(buf)?,
+            vote: H256::decode(buf)?,
+        })
+    }
+}
+
+/// `inter.LlrBlockVotes`, votes for consecutive blocks starting from `start`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlockVotes {
+    pub start: Block,
+    pub epoch: Epoch,
+    pub votes: Vec<H256>,
+}
+
+impl Encodable for BlockVotes {
+    fn encode(&self, out: &mut Writer) {
+        self.start.encode(out);
+        self.epoch.encode(out);
+        self.votes.encode(out);
+    }
+}
+
+impl Decodable for BlockVotes {
+    type Error = Error;
+
+    fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            start: Block::decode(buf)?,
+            epoch: Epoch::decode(buf)?,
+            votes: Vec::decode(buf)?,
+        })
+    }
+}
+
+/// `types.AccessTuple`, storage keys of an account accessed by a transaction.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccessTuple {
+    pub address: Address,
+    pub storage_keys: Vec<H256>,
+}
+
+impl Encodable for AccessTuple {
+    fn encode(&self, out: &mut Writer) {
+        self.address.encode(out);
+        self.storage_keys.encode(out);
+    }
+}
+
+impl Decodable for AccessTuple {
+    type Error = Error;
+
+    fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            address: Address::decode(buf)?,
+            storage_keys: Vec::decode(buf)?,
+        })
+    }
+}
+
+/// Transaction of version 1 and later events, laid out field by field as go-opera's
+/// `TransactionMarshalCSER` does.
+///
+/// Typed transactions start with six zero bits and the type. Those bits are the size bits
+/// of the nonce and the gas limit of legacy transactions, so a legacy transaction must
+/// have a gas limit above 255 to be told apart.
+///
+/// # Panics
+///
+/// Encoding panics for unknown types and for legacy transactions with a gas limit of 255
+/// or less, go-opera refuses to encode those as well. [`Transaction::try_encode`] returns
+/// an error instead.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Transaction {
+    /// One of [`Transaction::LEGACY`], [`Transaction::ACCESS_LIST`] and
+    /// [`Transaction::DYNAMIC_FEE`].
+    pub tx_type: u8,
+    pub nonce: u64,
+    pub gas: u64,
+    /// Only stored for dynamic fee transactions.
+    pub gas_tip_cap: U256,
+    /// Gas price, or the fee cap of dynamic fee transactions.
+    pub gas_price: U256,
+    pub value: U256,
+    pub to: Option<Address>,
+    pub data: Bytes,
+    pub v: U256,
+    pub r: U256,
+    pub s: U256,
+    /// Only stored for typed transactions, like the access list.
+    pub chain_id: U256,
+    pub access_list: Vec<AccessTuple>,
+}
+
+impl Transaction {
+    pub const LEGACY: u8 = 0;
+    pub const ACCESS_LIST: u8 = 1;
+    pub const DYNAMIC_FEE: u8 = 2;
+
+    /// Checks the rules the encoding relies on, see [`Transaction`].
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.tx_type > Self::DYNAMIC_FEE {
+            return Err(Error::Custom("unknown transaction type"));
+        }
+        if self.tx_type == Self::LEGACY && self.gas <= 0xFF {
+            return Err(Error::Custom(
+                "legacy transactions need a gas limit above 255",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Encodes the transaction, or returns the error of [`Transaction::validate`] without
+    /// writing anything.
+    pub fn try_encode(&self, out: &mut Writer) -> Result<(), Error> {
+        self.validate()?;
+        self.encode(out);
+        Ok(())
+    }
+}
+
+impl Encodable for Transaction {
+    fn encode(&self, out: &mut Writer) {
+        if let Err(Error::Custom(msg)) = self.validate() {
+            panic!("{msg}");
+        }
+        if self.tx_type != Self::LEGACY {
+            out.bits_w.write(6, 0);
+            self.tx_type.encode(out);
+        }
+
+        self.nonce.encode(out);
+        self.gas.encode(out);
+        if self.tx_type == Self::DYNAMIC_FEE {
+            self.gas_tip_cap.encode(out);
+        }
+        self.gas_price.encode(out);
+        self.value.encode(out);
+        self.to.encode(out);
+        (&*self.data).encode(out);
+        self.v.encode(out);
+        self.r.encode(out);
+        self.s.encode(out);
+        if self.tx_type != Self::LEGACY {
+            self.chain_id.encode(out);
+            self.access_list.encode(out);
+        }
+    }
+}
+
+impl Decodable for Transaction {
+    type Error = Error;
+
+    fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
+        let mut tx_type = Self::LEGACY;
+        if buf.bits_r.view(6)? == 0 {
+            buf.bits_r.read(6)?;
+            tx_type = u8::decode(buf)?;
+            if tx_type == Self::LEGACY || tx_type > Self::DYNAMIC_FEE {
+                return Err(Error::MalformedEncoding);
+            }
+        }
+
+        let mut this = Self {
+            tx_type,
+            nonce: u64::decode(buf)?,
+            gas: u64::decode(buf)?,
+            ..Default::default()
+        };
+        if tx_type == Self::DYNAMIC_FEE {
+            this.gas_tip_cap = U256::decode(buf)?;
+        }
+        this.gas_price = U256::decode(buf)?;
+        this.value = U256::decode(buf)?;
+        this.to = Option::decode(buf)?;
+        this.data = Bytes::decode(buf)?;
+        this.v = U256::decode(buf)?;
+        this.r = U256::decode(buf)?;
+        this.s = U256::decode(buf)?;
+        if tx_type != Self::LEGACY {
+            this.chain_id = U256::decode(buf)?;
+            this.access_list = Vec::decode(buf)?;
+        }
+
+        Ok(this)
+    }
+}
+
+/// `inter.EventPayload`, the signed event together with its payload.
+///
+/// Each payload part is present only when the matching flag of the header is set.
+///
+/// The layout of the transactions of version 1 and later events has not been checked
+/// against go-opera yet, see `tests/vectors/README.md`.
+///
+/// # Panics
+///
+/// Encoding panics if the event or one of the stored transactions would, see
+/// [`EventPayload::try_encode`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventPayload {
+    pub event: Event,
+    pub sig: H512,
+    /// Transactions of version 1 and later events.
+    pub txs: Vec<Transaction>,
+    /// RLP-encoded list of the transactions of version 0 events.
+    pub rlp_txs: Bytes,
+    /// RLP-encoded list of misbehaviour proofs.
+    pub misbehaviour_proofs: Bytes,
+    pub epoch_vote: EpochVote,
+    pub block_votes: BlockVotes,
+}
+
+impl EventPayload {
+    /// Checks the rules the encoding relies on for the event and the transactions which
+    /// are stored.
+    pub fn validate(&self) -> Result<(), Error> {
+        self.event.validate()?;
+        if self.event.any_txs && self.event.version > 0 {
+            self.txs.iter().try_for_each(Transaction::validate)?;
+        }
+        Ok(())
+    }
+
+    /// Encodes the payload, or returns the error of [`EventPayload::validate`] without
+    /// writing anything.
+    pub fn try_encode(&self, out: &mut Writer) -> Result<(), Error> {
+        self.validate()?;
+        self.encode(out);
+        Ok(())
+    }
+}
+
+impl Encodable for EventPayload {
+    fn encode(&self, out: &mut Writer) {
+        self.event.encode(out);
+        self.sig.encode(out);
+
+        if self.event.any_txs {
+            if self.event.version == 0 {
+                (&*self.rlp_txs).encode(out);
+            } else {
+                self.txs.encode(out);
+            }
+        }
+        if self.event.any_misbehaviour_proofs {
+            self.misbehaviour_proofs.encode(out);
+        }
+        if self.event.any_epoch_vote {
+            self.epoch_vote.encode(out);
+        }
+        if self.event.any_block_votes {
+            self.block_votes.encode(out);
+        }
+    }
+}
+
+impl Decodable for EventPayload {
+    type Error = Error;
+
+    fn decode(buf: &mut Reader<'_>) -> Result<Self, Self::Error> {
+        let mut this = Self {
+            event: Event::decode(buf)?,
+            sig: H512::decode(buf)?,
+            ..Default::default()
+        };
+
+        if this.event.any_txs {
+            if this.event.version == 0 {
+                this.rlp_txs = Bytes::decode(buf)?;
+            } else {
+                this.txs = Vec::decode(buf)?;
+            }
+        }
+        if this.event.any_misbehaviour_proofs {
+            this.misbehaviour_proofs = Bytes::decode(buf)?;
+        }
+        if this.event.any_epoch_vote {
+            this.epoch_vote = EpochVote::decode(buf)?;
+        }
+        if this.event.any_block_votes {
+            this.block_votes = BlockVotes::decode(buf)?;
+        }
+
+        Ok(this)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deserialize;
+    use alloc::vec;
+
+    fn event(version: u8) -> Event {
+        let parent = |lamport: u32, fill| {
+            let mut h = H256::repeat_byte(fill);
+            h[..4].copy_from_slice(&0x1234_u32.to_be_bytes());
+            h[4..8].copy_from_slice(&lamport.to_be_bytes());
+            EventHash(h)
+        };
+
+        Event {
+            version,
+            net_fork_id: if version > 0 { 3 } else { 0 },
+            epoch: Epoch(0x1234),
+            seq: Seq(7),
+            frame: Frame(5),
+            creator: ValidatorId(42),
+            lamport: Lamport(100),
+            creation_time: Timestamp(1_700_000_000_000_000_000),
+            median_time: Timestamp(1_699_999_999_000_000_000),
+            gas_power_used: 28_000,
+            gas_power_left: GasPowerLeft {
+                gas: [1_000_000, 5_000_000],
+            },
+            parents: vec![parent(99, 0xAA), parent(1, 0xBB)],
+            prev_epoch_hash: Some(H256::repeat_byte(0xCC)),
+            any_txs: true,
+            any_misbehaviour_proofs: false,
+            any_epoch_vote: version > 0,
+            any_block_votes: version > 0,
+            payload_hash: H256::repeat_byte(0xDD),
+            extra: Bytes::from_static(b"extra"),
+        }
+    }
+
+    fn encode<T: Encodable>(v: &T) -> Vec<u8> {
+        let mut w = Writer::new();
+        v.encode(&mut w);
+        w.output()
+    }
+
+    #[test]
+    fn event_roundtrip() {
+        for version in [0, 1] {
+            let e = event(version);
+            assert_eq!(deserialize::<Event>(&encode(&e)), Ok(e));
+        }
+
+        // without payload the hash is not stored
+        let e = Event {
+            any_txs: false,
+            payload_hash: H256::zero(),
+            ..event(0)
+        };
+        assert_eq!(deserialize::<Event>(&encode(&e)), Ok(e));
+    }
+
+    #[test]
+    fn event_version_zero_is_non_canonical() {
+        let mut w = Writer::new();
+        w.bits_w.write(2, 0);
+        0_u8.encode(&mut w);
+        assert_eq!(
+            deserialize::<Event>(&w.output()),
+            Err(Error::NonCanonicalEncoding)
+        );
+    }
+
+    #[test]
+    fn parent_lamport_above_event() {
+        let mut e = event(0);
+        let mut parent = e.parents[0];
+        parent.0[4..8].copy_from_slice(&(*e.lamport + 1).to_be_bytes());
+        e.parents = vec![parent];
+        let mut w = Writer::new();
+        assert_eq!(
+            e.try_encode(&mut w),
+            Err(Error::Custom(
+                "parents must not have a higher Lamport time than the event"
+            ))
+        );
+        assert_eq!(w.output(), Writer::new().output());
+
+        // a difference above the event's Lamport time is not wrapped around on decoding
+        parent.0[4..8].copy_from_slice(&0_u32.to_be_bytes());
+        e.parents = vec![parent];
+        let mut encoded = encode(&e);
+        // the epoch takes the first two bytes, the Lamport time the third one
+        assert_eq!(encoded[2], 100);
+        encoded[2] = 99;
+        assert_eq!(
+            deserialize::<Event>(&encoded),
+            Err(Error::MalformedEncoding)
+        );
+    }
+
+    #[test]
+    fn parent_other_epoch() {
+        let mut e = event(1);
+        e.parents[0].0[..4].copy_from_slice(&0x1233_u32.to_be_bytes());
+        assert_eq!(
+            e.validate(),
+            Err(Error::Custom("parents must be from the event's epoch"))
+        );
+    }
+
+    #[test]
+    fn event_version_zero_low_epoch() {
+        let e = Event {
+            epoch: Epoch(255),
+            ..event(0)
+        };
+        assert_eq!(
+            e.try_encode(&mut Writer::new()),
+            Err(Error::Custom(
+                "version 0 events need an epoch of at least 256"
+            ))
+        );
+    }
+
+    #[test]
+    #[should_panic = "version 0 events need an epoch of at least 256"]
+    fn event_version_zero_low_epoch_panics() {
+        encode(&Event {
+            epoch: Epoch(255),
+            ..event(0)
+        });
+    }
+
+    fn tx(tx_type: u8) -> Transaction {
+        Transaction {
+            tx_type,
+            nonce: 1,
+            gas: 21_000,
+            gas_tip_cap: if tx_type == Transaction::DYNAMIC_FEE {
+                U256::from(2_000_000_000_u64)
+            } else {
+                U256::zero()
+            },
+            gas_price: U256::from(100_000_000_000_u64),
+            value: U256::exp10(18),
+            to: Some(Address::repeat_byte(0x55)),
+            data: Bytes::from_static(&[0xA9, 0x05, 0x9C, 0xBB]),
+            v: U256::from(if tx_type == Transaction::LEGACY {
+                0x1F9
+            } else {
+                1
+            }),
+            r: U256::MAX,
+            s: U256::one(),
+            chain_id: if tx_type == Transaction::LEGACY {
+                U256::zero()
+            } else {
+                U256::from(250)
+            },
+            access_list: if tx_type == Transaction::LEGACY {
+                vec![]
+            } else {
+                vec![AccessTuple {
+                    address: Address::repeat_byte(0x66),
+                    storage_keys: vec![H256::repeat_byte(0x77)],
+                }]
+            },
+        }
+    }
+
+    #[test]
+    fn transaction_roundtrip() {
+        for tx_type in [
+            Transaction::LEGACY,
+            Transaction::ACCESS_LIST,
+            Transaction::DYNAMIC_FEE,
+        ] {
+            let tx = tx(tx_type);
+            let encoded = encode(&tx);
+            assert_eq!(deserialize::<Transaction>(&encoded), Ok(tx));
+
+            // typed transactions start with six zero bits and the type
+            let sections = crate::sections(&encoded).unwrap();
+            assert_eq!(sections.bits[0] & 0b11_1111 == 0, tx_type != 0);
+            if tx_type != 0 {
+                assert_eq!(sections.bytes[0], tx_type);
+            }
+        }
+
+        let contract_creation = Transaction {
+            to: None,
+            ..tx(Transaction::DYNAMIC_FEE)
+        };
+        assert_eq!(
+            deserialize::<Transaction>(&encode(&contract_creation)),
+            Ok(contract_creation)
+        );
+    }
+
+    #[test]
+    fn invalid_transactions() {
+        let low_gas = Transaction {
+            gas: 255,
+            ..tx(Transaction::LEGACY)
+        };
+        assert_eq!(
+            low_gas.try_encode(&mut Writer::new()),
+            Err(Error::Custom(
+                "legacy transactions need a gas limit above 255"
+            ))
+        );
+        let unknown = Transaction {
+            tx_type: 3,
+            ..tx(Transaction::DYNAMIC_FEE)
+        };
+        assert_eq!(
+            unknown.validate(),
+            Err(Error::Custom("unknown transaction type"))
+        );
+
+        let payload = EventPayload {
+            event: event(1),
+            txs: vec![tx(Transaction::LEGACY), unknown],
+            ..Default::default()
+        };
+        assert_eq!(
+            payload.try_encode(&mut Writer::new()),
+            Err(Error::Custom("unknown transaction type"))
+        );
+        // transactions are not stored without the flag
+        let payload = EventPayload {
+            event: Event {
+                any_txs: false,
+                ..payload.event
+            },
+            ..payload
+        };
+        assert_eq!(payload.validate(), Ok(()));
+    }
+
+    #[test]
+    #[should_panic = "legacy transactions need a gas limit above 255"]
+    fn legacy_transaction_low_gas() {
+        encode(&Transaction {
+            gas: 255,
+            ..tx(Transaction::LEGACY)
+        });
+    }
+
+    #[test]
+    fn payload_roundtrip() {
+        let v0 = EventPayload {
+            event: event(0),
+            sig: H512::repeat_byte(0x11),
+            rlp_txs: Bytes::from_static(&[0xC0]),
+            ..Default::default()
+        };
+        assert_eq!(deserialize::<EventPayload>(&encode(&v0)), Ok(v0));
+
+        let v1 = EventPayload {
+            event: event(1),
+            sig: H512::repeat_byte(0x11),
+            txs: vec![tx(Transaction::LEGACY), tx(Transaction::DYNAMIC_FEE)],
+            rlp_txs: Bytes::new(),
+            misbehaviour_proofs: Bytes::new(),
+            epoch_vote: EpochVote {
+                epoch: Epoch(0x1233),
+                vote: H256::repeat_byte(0x22),
+            },
+            block_votes: BlockVotes {
+                start: Block(1000),
+                epoch: Epoch(0x1234),
+                votes: vec![H256::repeat_byte(0x33), H256::repeat_byte(0x44)],
+            },
+        };
+        assert_eq!(deserialize::<EventPayload>(&encode(&v1)), Ok(v1));
+    }
+}
@@ -1,4 +1,5 @@
 use super::{bits, error::*, fast, Decodable, U56};
+use alloc::vec::Vec;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct Writer {
@@ -10,6 +11,24 @@ pub struct Writer {
 pub struct Reader<'a> {
     pub bits_r: bits::Reader<'a>,
     pub bytes_r: fast::Reader<'a>,
+    pub strictness: Strictness,
+    violations: Vec<Violation>,
+}
+
+/// Lengths of both sections of a [`Writer`], see [`Writer::checkpoint`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WriterCheckpoint {
+    bits_len: usize,
+    bit_offset: usize,
+    bytes_len: usize,
+}
+
+/// Position of a [`Reader`] in both sections, see [`Reader::checkpoint`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReaderCheckpoint {
+    bits: usize,
+    bytes: usize,
+    violations: usize,
 }
 
 impl Writer {
@@ -25,6 +44,64 @@ impl Writer {
     pub fn output(self) -> Vec<u8> {
         crate::binary::binary_from_cser(self.bits_w.view_bytes(), self.bytes_w.buf)
     }
+
+    /// Writes `v` into the bytes section as a compact integer, seven bits per byte.
+    pub fn write_compact(&mut self, v: u64) {
+        write_uint64_compact(&mut self.bytes_w, v)
+    }
+
+    /// Writes `v` into the bits section as exactly `bits` bits.
+    ///
+    /// # Panics
+    ///
+    /// If `v` does not fit into `bits` bits, or `bits` is over 64.
+    pub fn write_bits(&mut self, bits: usize, v: u64) {
+        assert!(bits <= 64, "at most 64 bits fit into a u64");
+        assert!(
+            bits == 64 || v >> bits == 0,
+            "{v} does not fit into {bits} bits"
+        );
+        // the bits writer takes at most a `usize` at a time
+        let (mut bits, mut v) = (bits, v);
+        while bits > 0 {
+            let n = bits.min(32);
+            self.bits_w.write(n, (v & ((1 << n) - 1)) as usize);
+            v >>= n;
+            bits -= n;
+        }
+    }
+
+    /// Length [`Writer::output`] would have now, footer included.
+    pub fn current_output_len(&self) -> usize {
+        let bits_len = self.bits_w.view_bytes().len();
+        self.bytes_w.buf.len() + bits_len + compact_len(bits_len as u64)
+    }
+
+    /// Marks the current end of the output, to drop what is written after it with
+    /// [`Writer::rollback`].
+    pub fn checkpoint(&self) -> WriterCheckpoint {
+        WriterCheckpoint {
+            bits_len: self.bits_w.view_bytes().len(),
+            bit_offset: self.bits_w.bit_offset(),
+            bytes_len: self.bytes_w.buf.len(),
+        }
+    }
+
+    /// Drops everything written since `checkpoint`.
+    ///
+    /// # Panics
+    ///
+    /// If anything written before `checkpoint` was rolled back already.
+    pub fn rollback(&mut self, checkpoint: WriterCheckpoint) {
+        assert!(
+            checkpoint.bits_len <= self.bits_w.view_bytes().len()
+                && checkpoint.bytes_len <= self.bytes_w.buf.len(),
+            "checkpoint is ahead of the writer"
+        );
+        self.bits_w
+            .truncate(checkpoint.bits_len, checkpoint.bit_offset);
+        self.bytes_w.buf.truncate(checkpoint.bytes_len);
+    }
 }
 
 impl Default for Writer {
@@ -48,24 +125,46 @@ pub(crate) fn write_uint64_compact(bytes_w: &mut fast::Writer, mut v: u64) {
     }
 }
 
+/// Length of the compact encoding of `v`.
+pub(crate) fn compact_len(v: u64) -> usize {
+    (64 - v.leading_zeros() as usize).div_ceil(7).max(1)
+}
+
+/// Only the std framing reads lengths on their own.
+#[cfg(feature = "std")]
 pub(crate) fn read_uint64_compact(bytes_r: &mut fast::Reader) -> Result<u64, Error> {
+    match read_uint64_compact_lenient(bytes_r)? {
+        (v, true) => Ok(v),
+        (_, false) => Err(Error::NonCanonicalEncoding),
+    }
+}
+
+/// Reads the value along with whether its encoding is canonical.
+pub(crate) fn read_uint64_compact_lenient(
+    bytes_r: &mut fast::Reader,
+) -> Result<(u64, bool), Error> {
     let mut v = 0_u64;
     let mut stop = false;
+    let mut canonical = true;
     let mut i = 0;
     while !stop {
         let chunk = u64::from(bytes_r.read_byte()?);
         stop = (chunk & 0b10000000) != 0;
         let word = chunk & 0b01111111;
+        // the 10th byte holds the top bit of a u64 and must be the last one
+        if i == 9 && (!stop || word > 1) {
+            return Err(Error::MalformedEncoding);
+        }
         v |= word << (i * 7);
         // last byte cannot be zero
         if i > 0 && stop && word == 0 {
-            return Err(Error::NonCanonicalEncoding);
+            canonical = false;
         }
 
         i += 1;
     }
 
-    Ok(v)
+    Ok((v, canonical))
 }
 
 fn write_uint64_bit_compact(bytes_w: &mut fast::Writer, mut v: u64, min_size: usize) -> usize {
@@ -79,7 +178,12 @@ fn write_uint64_bit_compact(bytes_w: &mut fast::Writer, mut v: u64, min_size: us
     size
 }
 
-fn read_uint64_bit_compact(bytes_r: &mut fast::Reader, size: usize) -> Result<u64, Error> {
+/// Reads the value along with whether its encoding is canonical.
+fn read_uint64_bit_compact(
+    bytes_r: &mut fast::Reader,
+    size: usize,
+    min_size: usize,
+) -> Result<(u64, bool), Error> {
     let mut v = 0_u64;
     let mut last = 0_u8;
 
@@ -89,17 +193,136 @@ fn read_uint64_bit_compact(bytes_r: &mut fast::Reader, size: usize) -> Result<u6
         last = b;
     }
 
-    if size > 1 && last == 0 {
-        return Err(Error::NonCanonicalEncoding);
-    }
-
-    Ok(v)
+    // anything above the minimal size must not end with a zero byte
+    Ok((v, size <= min_size || last != 0))
 }
 
 impl<'a> Reader<'a> {
+    pub fn new(bbits: &'a [u8], bbytes: &'a [u8]) -> Self {
+        Self {
+            bits_r: bits::Reader::new(bbits),
+            bytes_r: fast::Reader::new(bbytes),
+            strictness: Strictness::default(),
+            violations: Vec::new(),
+        }
+    }
+
+    /// Reports a non-canonical encoding which go-opera rejects as well: a violation in
+    /// lenient mode, an error otherwise.
+    pub fn non_canonical(&mut self, violation: Violation) -> Result<(), Error> {
+        match self.strictness {
+            Strictness::Strict | Strictness::Compatible => Err(Error::NonCanonicalEncoding),
+            Strictness::Lenient => {
+                self.violations.push(violation);
+                Ok(())
+            }
+        }
+    }
+
+    /// Reports a non-canonical encoding which go-opera accepts: an error in strict mode,
+    /// a violation otherwise.
+    pub fn tolerated_non_canonical(&mut self, violation: Violation) -> Result<(), Error> {
+        match self.strictness {
+            Strictness::Strict => Err(Error::NonCanonicalEncoding),
+            Strictness::Compatible | Strictness::Lenient => {
+                self.violations.push(violation);
+                Ok(())
+            }
+        }
+    }
+
+    /// Violations collected so far, see [`Strictness`].
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+
+    pub(crate) fn take_violations(&mut self) -> Vec<Violation> {
+        core::mem::take(&mut self.violations)
+    }
+
+    /// Adds violations accepted by a reader with the same strictness.
+    pub(crate) fn extend_violations(&mut self, violations: Vec<Violation>) {
+        self.violations.extend(violations)
+    }
+
+    /// Number of bits consumed from the bits section.
+    pub fn bits_position(&self) -> usize {
+        self.bits_r.position()
+    }
+
+    /// Number of bytes consumed from the bytes section.
+    pub fn bytes_position(&self) -> usize {
+        self.bytes_r.position()
+    }
+
+    /// Snapshot of the current position, to return to with [`Reader::restore`].
+    pub fn checkpoint(&self) -> ReaderCheckpoint {
+        ReaderCheckpoint {
+            bits: self.bits_position(),
+            bytes: self.bytes_position(),
+            violations: self.violations.len(),
+        }
+    }
+
+    /// Moves back to `checkpoint`, forgetting the violations collected since.
+    ///
+    /// # Panics
+    ///
+    /// If `checkpoint` was taken from a reader over other input.
+    pub fn restore(&mut self, checkpoint: ReaderCheckpoint) {
+        self.bits_r
+            .seek(checkpoint.bits)
+            .and_then(|_| self.bytes_r.seek(checkpoint.bytes))
+            .expect("checkpoint of another reader");
+        self.violations.truncate(checkpoint.violations);
+    }
+
+    /// Decodes the next value without consuming it.
+    pub fn peek<T: Decodable>(&mut self) -> Result<T, T::Error> {
+        let checkpoint = self.checkpoint();
+        let res = T::decode(self);
+        self.restore(checkpoint);
+        res
+    }
+
+    /// Reads `bits` bits written by [`Writer::write_bits`].
+    pub fn read_bits(&mut self, bits: usize) -> Result<u64, Error> {
+        if bits > 64 {
+            return Err(Error::OverFlowError);
+        }
+        let mut v = 0;
+        let mut shift = 0;
+        while shift < bits {
+            let n = (bits - shift).min(32);
+            v |= (self.bits_r.read(n)? as u64) << shift;
+            shift += n;
+        }
+        Ok(v)
+    }
+
+    /// Reads a compact integer written by [`Writer::write_compact`].
+    pub fn read_compact(&mut self) -> Result<u64, Error> {
+        let offset = self.bytes_r.position();
+        let (v, canonical) = read_uint64_compact_lenient(&mut self.bytes_r)?;
+        if !canonical {
+            self.non_canonical(Violation::IntegerZeroHighByte { offset })?;
+        }
+        Ok(v)
+    }
+
     pub fn read_u64_bits(&mut self, min_size: usize, bits_for_size: usize) -> Result<u64, Error> {
-        let size = self.bits_r.read(bits_for_size) + min_size;
-        read_uint64_bit_compact(&mut self.bytes_r, size)
+        let size = self.bits_r.read(bits_for_size)? + min_size;
+        let offset = self.bytes_r.position();
+        let (v, canonical) = read_uint64_bit_compact(&mut self.bytes_r, size, min_size)?;
+        if !canonical {
+            // go-opera only checks the high byte of integers longer than one byte
+            if size > 1 {
+                self.non_canonical(Violation::IntegerZeroHighByte { offset })?;
+            } else {
+                self.tolerated_non_canonical(Violation::IntegerZeroHighByte { offset })?;
+            }
+        }
+        Ok(v)
     }
 
     pub fn slice_bytes(&mut self, max_len: usize) -> Result<&[u8], Error> {
@@ -124,6 +347,7 @@ mod tests {
     use super::*;
     use crate::Encodable;
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_uint64_compact() {
         for (fixture, expected) in [
@@ -138,18 +362,143 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_uint64_compact_max_len() {
+        let mut r =
+            fast::Reader::new(&[0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0x81]);
+        assert_eq!(Ok((u64::MAX, true)), read_uint64_compact_lenient(&mut r));
+
+        for fixture in [
+            // no stop flag on the 10th byte
+            &[0x01_u8; 11][..],
+            // bits beyond the 64th
+            &[0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0x82],
+        ] {
+            let mut r = fast::Reader::new(fixture);
+            assert_eq!(
+                Err(Error::MalformedEncoding),
+                read_uint64_compact_lenient(&mut r)
+            );
+        }
+    }
+
     #[test]
     fn test_uint64_bit_compact() {
         for (fixture, expected) in [
-            (&[0b11111111_u8, 0b00111111_u8] as &[u8], Ok(0x3fff_u64)),
+            (
+                &[0b11111111_u8, 0b00111111_u8] as &[u8],
+                Ok((0x3fff_u64, true)),
+            ),
             (
                 &[0b01111111_u8, 0b01111111_u8, 0b00000000_u8] as &[u8],
-                Err(Error::NonCanonicalEncoding),
+                Ok((0x7f7f_u64, false)),
             ),
         ] {
             let mut r = fast::Reader::new(fixture);
-            assert_eq!(expected, read_uint64_bit_compact(&mut r, fixture.len()));
+            assert_eq!(expected, read_uint64_bit_compact(&mut r, fixture.len(), 1));
+        }
+
+        // zero is written as an empty slice when the minimal size is 0
+        let mut r = fast::Reader::new(&[0]);
+        assert_eq!(Ok((0, false)), read_uint64_bit_compact(&mut r, 1, 0));
+        let mut r = fast::Reader::new(&[0]);
+        assert_eq!(Ok((0, true)), read_uint64_bit_compact(&mut r, 1, 1));
+    }
+
+    #[test]
+    fn speculative() {
+        let mut w = Writer::new();
+        let mut outputs = vec![w.clone().output()];
+        let mut checkpoints = vec![w.checkpoint()];
+        for i in 0..40_u64 {
+            (i % 3 == 0).encode(&mut w);
+            (i << (i % 64)).encode(&mut w);
+            assert_eq!(w.current_output_len(), w.clone().output().len());
+            outputs.push(w.clone().output());
+            checkpoints.push(w.checkpoint());
+        }
+
+        // roll back both mid-byte and byte-aligned bit offsets
+        while let Some(checkpoint) = checkpoints.pop() {
+            w.rollback(checkpoint);
+            assert_eq!(w.current_output_len(), w.clone().output().len());
+            assert_eq!(w.clone().output(), outputs.pop().unwrap());
+        }
+    }
+
+    #[test]
+    fn compact_len() {
+        for v in [0, 1, 127, 128, 1 << 14, u64::MAX] {
+            let mut w = fast::Writer::new(vec![]);
+            write_uint64_compact(&mut w, v);
+            assert_eq!(super::compact_len(v), w.buf.len());
+        }
+    }
+
+    #[test]
+    fn lookahead() {
+        let mut w = Writer::new();
+        true.encode(&mut w);
+        0x1234_u16.encode(&mut w);
+        true.encode(&mut w);
+        0_u64.encode(&mut w);
+
+        let mut r = Reader::new(w.bits_w.view_bytes(), &w.bytes_w.buf);
+        assert_eq!(r.peek::<bool>(), Ok(true));
+        assert_eq!(r.peek::<bool>(), Ok(true));
+        assert_eq!((r.bits_position(), r.bytes_position()), (0, 0));
+
+        let start = r.checkpoint();
+        assert_eq!(bool::decode(&mut r), Ok(true));
+        assert_eq!(u16::decode(&mut r), Ok(0x1234));
+        assert_eq!((r.bits_position(), r.bytes_position()), (2, 2));
+
+        // violations found while peeking are forgotten
+        r.strictness = Strictness::Lenient;
+        assert_eq!(r.peek::<i64>(), Ok(0));
+        assert!(r.violations().is_empty());
+        assert_eq!(i64::decode(&mut r), Ok(0));
+        assert_eq!(r.violations().len(), 1);
+
+        r.restore(start);
+        assert_eq!((r.bits_position(), r.bytes_position()), (0, 0));
+        assert!(r.violations().is_empty());
+        assert_eq!(bool::decode(&mut r), Ok(true));
+        assert_eq!(u16::decode(&mut r), Ok(0x1234));
+    }
+
+    #[test]
+    fn bit_fields() {
+        let fields = [
+            (1, 1),
+            (3, 5),
+            (7, 0),
+            (13, 0x1abc),
+            (33, 0x1_2345_6789),
+            (64, u64::MAX),
+            (0, 0),
+            (2, 2),
+        ];
+
+        let mut w = Writer::new();
+        for (bits, v) in fields {
+            w.write_bits(bits, v);
+        }
+        let total: usize = fields.iter().map(|(bits, _)| bits).sum();
+        assert_eq!(w.bits_w.view_bytes().len(), total.div_ceil(8));
+        assert!(w.bytes_w.buf.is_empty());
+
+        let mut r = Reader::new(w.bits_w.view_bytes(), &w.bytes_w.buf);
+        for (bits, v) in fields {
+            assert_eq!(r.read_bits(bits), Ok(v));
         }
+        assert_eq!(r.read_bits(8), Err(Error::MalformedEncoding));
+    }
+
+    #[test]
+    #[should_panic = "does not fit"]
+    fn bit_field_overflow() {
+        Writer::new().write_bits(3, 8);
     }
 
     #[test]
@@ -163,12 +512,36 @@ mod tests {
         true.encode(&mut w);
         0_u64.encode(&mut w);
 
-        let mut r = Reader {
-            bits_r: bits::Reader::new(w.bits_w.view_bytes()),
-            bytes_r: fast::Reader::new(&w.bytes_w.buf),
-        };
+        let mut r = Reader::new(w.bits_w.view_bytes(), &w.bytes_w.buf);
 
         assert_eq!(i64::decode(&mut r), Ok(0));
         assert_eq!(i64::decode(&mut r), Err(Error::NonCanonicalEncoding));
+
+        // magnitude out of range
+        let mut w = Writer::new();
+        false.encode(&mut w);
+        (1_u64 << 63).encode(&mut w);
+        true.encode(&mut w);
+        (1_u64 << 63).encode(&mut w);
+        true.encode(&mut w);
+        ((1_u64 << 63) + 1).encode(&mut w);
+
+        let mut r = Reader::new(w.bits_w.view_bytes(), &w.bytes_w.buf);
+
+        // go-opera wraps them around, only strict mode rejects them
+        assert_eq!(i64::decode(&mut r), Ok(i64::MIN));
+        assert_eq!(i64::decode(&mut r), Ok(i64::MIN));
+        assert_eq!(i64::decode(&mut r), Ok(i64::MAX));
+        assert_eq!(
+            r.violations(),
+            [
+                Violation::SignedOverflow { offset: 0 },
+                Violation::SignedOverflow { offset: 16 }
+            ]
+        );
+
+        let mut r = Reader::new(w.bits_w.view_bytes(), &w.bytes_w.buf);
+        r.strictness = Strictness::Strict;
+        assert_eq!(i64::decode(&mut r), Err(Error::NonCanonicalEncoding));
     }
 }
@@ -0,0 +1,71 @@
+//! Helpers for testing `Encodable`/`Decodable` implementations.
+
+use super::{
+    binary::{deserialize, deserialize_cb_with},
+    error::{Error, Strictness},
+    Decodable, Encodable, Writer, U56,
+};
+use core::{fmt::Debug, ops::RangeInclusive};
+use proptest::{
+    arbitrary::Arbitrary,
+    strategy::{Map, Strategy},
+};
+
+fn encode<T: Encodable>(value: &T) -> Vec<u8> {
+    let mut w = Writer::new();
+    value.encode(&mut w);
+    w.output()
+}
+
+/// Encodes `value`, decodes it back and checks that the result is equal to `value`,
+/// that its encoding is canonical and that skipping it consumes exactly the encoding.
+/// Also checks that both traits agree on whether sequences of `T` are byte strings.
+/// Returns the encoded bytes.
+pub fn assert_roundtrip<T>(value: &T) -> Vec<u8>
+where
+    T: Encodable + Decodable + PartialEq + Debug,
+    T::Error: From<Error> + Debug,
+{
+    assert_eq!(
+        T::IS_BYTE,
+        T::FROM_BYTE.is_some(),
+        "Encodable::IS_BYTE and Decodable::FROM_BYTE disagree"
+    );
+    let buf = encode(value);
+    let decoded = deserialize::<T>(&buf)
+        .unwrap_or_else(|e| panic!("failed to decode {value:?} from {buf:02x?}: {e:?}"));
+    assert_eq!(
+        &decoded, value,
+        "decoded value differs, encoding: {buf:02x?}"
+    );
+    assert_canonical::<T>(&buf);
+    if let Err(e) = deserialize_cb_with(&buf, Strictness::Strict, T::skip) {
+        panic!("failed to skip {value:?} in {buf:02x?}: {e:?}");
+    }
+
+    buf
+}
+
+/// Decodes `buf` and checks that encoding the decoded value reproduces `buf` exactly.
+pub fn assert_canonical<T>(buf: &[u8])
+where
+    T: Encodable + Decodable + Debug,
+    T::Error: From<Error> + Debug,
+{
+    let decoded =
+        deserialize::<T>(buf).unwrap_or_else(|e| panic!("failed to decode {buf:02x?}: {e:?}"));
+    assert_eq!(
+        encode(&decoded),
+        buf,
+        "encoding of {decoded:?} is not canonical"
+    );
+}
+
+impl Arbitrary for U56 {
+    type Parameters = ();
+    type Strategy = Map<RangeInclusive<u64>, fn(u64) -> Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        (0..=*U56::MAX).prop_map(U56)
+    }
+}
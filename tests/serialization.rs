@@ -1,15 +1,25 @@
-use cser::{deserialize, *};
+use arrayvec::ArrayVec;
+use cser::{testing::*, *};
 use hex_literal::hex;
+use proptest::prelude::*;
 
-#[derive(Clone, Debug, PartialEq, Encodable, Decodable)]
+#[derive(Clone, Debug, PartialEq, Encodable, Decodable, CserSchema)]
 struct DeriveTest {
     field_a: u32,
     field_b: u64,
 }
 
-#[derive(Clone, Debug, PartialEq, EncodableWrapper, DecodableWrapper)]
+#[derive(Clone, Debug, PartialEq, EncodableWrapper, DecodableWrapper, CserSchema)]
 struct DeriveWrapperTest(DeriveTest);
 
+#[derive(Clone, Debug, PartialEq, Encodable, Decodable, CserSchema)]
+struct SchemaTest {
+    flags: Vec<bool>,
+    inner: Option<DeriveWrapperTest>,
+    id: [u8; 4],
+    payload: bytes::Bytes,
+}
+
 #[test]
 fn encodable_derive_equivalence() {
     let field_a = 0x1234_5678;
@@ -26,6 +36,508 @@ fn encodable_derive_equivalence() {
     assert_eq!(writer_derive, writer_manual);
 }
 
+#[test]
+fn wrapper_derive_roundtrip() {
+    let inner = DeriveTest {
+        field_a: 0x1234_5678,
+        field_b: 0x1234_5678_9abc_def0,
+    };
+
+    assert_eq!(
+        assert_roundtrip(&DeriveWrapperTest(inner.clone())),
+        assert_roundtrip(&inner)
+    );
+}
+
+#[test]
+fn derived_schema() {
+    let schema = SchemaTest::schema();
+    assert_eq!(
+        schema.to_string(),
+        "struct DeriveTest { field_a: u32, field_b: u64, }\n\
+         struct DeriveWrapperTest(DeriveTest);\n\
+         struct SchemaTest { flags: vec<bool>, inner: option<DeriveWrapperTest>, id: [u8; 4], payload: bytes, }\n\
+         SchemaTest"
+    );
+    assert_eq!(
+        schema.to_string().parse::<dynamic::Schema>().unwrap(),
+        schema
+    );
+
+    let value = SchemaTest {
+        flags: vec![true, false],
+        inner: Some(DeriveWrapperTest(DeriveTest {
+            field_a: 1,
+            field_b: 1 << 40,
+        })),
+        id: [1, 2, 3, 4],
+        payload: bytes::Bytes::from_static(b"payload"),
+    };
+    let encoded = assert_roundtrip(&value);
+    let decoded = dynamic::decode_with_schema(&schema, &encoded).unwrap();
+    assert_eq!(
+        dynamic::encode_with_schema(&schema, &decoded).unwrap(),
+        encoded
+    );
+}
+
+mod first {
+    #[derive(cser::Encodable, cser::CserSchema)]
+    pub struct Id(pub u32);
+}
+
+mod second {
+    #[derive(cser::Encodable, cser::CserSchema)]
+    pub struct Id(pub [u8; 4]);
+}
+
+#[derive(Encodable, CserSchema)]
+struct Ids {
+    first: first::Id,
+    second: second::Id,
+}
+
+#[test]
+fn schema_name_collisions() {
+    let schema = Ids::schema();
+    assert_eq!(
+        schema.to_string(),
+        "struct Ids { first: serialization_first_Id, second: serialization_second_Id, }\n\
+         struct serialization_first_Id(u32);\n\
+         struct serialization_second_Id([u8; 4]);\n\
+         Ids"
+    );
+    assert_eq!(
+        schema.to_string().parse::<dynamic::Schema>().unwrap(),
+        schema
+    );
+
+    let mut w = Writer::new();
+    Ids {
+        first: first::Id(7),
+        second: second::Id([1, 2, 3, 4]),
+    }
+    .encode(&mut w);
+    assert_eq!(
+        dynamic::decode_with_schema(&schema, &w.output()).unwrap(),
+        dynamic::Value::Struct(vec![
+            (
+                "first".into(),
+                dynamic::Value::Seq(vec![dynamic::Value::Uint(7)])
+            ),
+            (
+                "second".into(),
+                dynamic::Value::Seq(vec![dynamic::Value::Bytes(vec![1, 2, 3, 4])])
+            ),
+        ])
+    );
+}
+
+#[derive(Debug, Decodable)]
+struct LazyTest {
+    epoch: u32,
+    payload: LazyField<Vec<String>>,
+    creator: [u8; 4],
+}
+
+#[test]
+fn lazy_field() {
+    let payload = vec!["a".to_string(), "bc".to_string()];
+    let mut w = Writer::new();
+    7_u32.encode(&mut w);
+    payload.encode(&mut w);
+    [1_u8, 2, 3, 4].encode(&mut w);
+    let encoded = w.output();
+
+    let lazy = deserialize::<LazyTest>(&encoded).unwrap();
+    assert_eq!((lazy.epoch, lazy.creator), (7, [1, 2, 3, 4]));
+    assert_eq!(lazy.payload.decode_from(&encoded).unwrap(), payload);
+}
+
+#[derive(Debug, PartialEq, Encodable, Decodable)]
+#[cser(version = 1)]
+struct HeaderV1 {
+    epoch: u32,
+    legacy_fork: u64,
+}
+
+#[derive(Debug, PartialEq, Encodable, Decodable)]
+#[cser(version = 3, migrate = Header::migrate)]
+struct Header {
+    epoch: u32,
+    #[cser(until = 2)]
+    legacy_fork: u64,
+    #[cser(since = 2)]
+    fork: u16,
+    #[cser(since = 3)]
+    flags: Vec<bool>,
+}
+
+impl Header {
+    fn migrate(&mut self, version: u64) -> anyhow::Result<()> {
+        if version < 2 {
+            self.fork = self.legacy_fork.try_into()?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn versioned_derive() {
+    let old = assert_roundtrip(&HeaderV1 {
+        epoch: 7,
+        legacy_fork: 3,
+    });
+    assert_eq!(
+        deserialize::<Header>(&old).unwrap(),
+        Header {
+            epoch: 7,
+            legacy_fork: 3,
+            fork: 3,
+            flags: vec![],
+        }
+    );
+
+    let current = Header {
+        epoch: 7,
+        legacy_fork: 0,
+        fork: 3,
+        flags: vec![true],
+    };
+    let encoded = assert_roundtrip(&current);
+    // the version goes first, the dropped field is not written
+    assert_eq!(encoded[0], 0x83);
+    assert!(deserialize::<HeaderV1>(&encoded).is_err());
+
+    let mut w = Writer::new();
+    w.write_compact(4);
+    7_u32.encode(&mut w);
+    3_u16.encode(&mut w);
+    vec![true].encode(&mut w);
+    assert!(deserialize::<Header>(&w.output()).is_err());
+
+    // versions longer than a u64 are rejected instead of overflowing
+    let mut overlong = vec![0x01; 11];
+    overlong.push(0x80);
+    assert!(deserialize::<Header>(&overlong).is_err());
+}
+
+#[derive(Clone, Debug, PartialEq, Encodable, Decodable, CserSchema)]
+struct Signed<T, M> {
+    payload: T,
+    parents: Vec<Option<T>>,
+    sig: [u8; 4],
+    // not bound, so that markers need not be encodable
+    _marker: std::marker::PhantomData<M>,
+}
+
+#[derive(Clone, Debug, PartialEq, EncodableWrapper, DecodableWrapper)]
+struct SignedWrapper<T>(Signed<T, ()>);
+
+#[derive(CserSchema)]
+struct Signatures {
+    _narrow: Signed<u32, ()>,
+    _wide: Signed<u64, ()>,
+}
+
+trait Kind {
+    type Id;
+}
+
+#[derive(Debug, PartialEq)]
+struct EventKind;
+
+impl Kind for EventKind {
+    type Id = [u8; 4];
+}
+
+#[derive(Debug, PartialEq, Encodable, Decodable, CserSchema)]
+#[cser(bound(
+    encode = "K::Id: Encodable",
+    decode = "K::Id: Decodable<Error = Error>",
+    schema = "K::Id: CserSchema",
+))]
+struct Ref<K: Kind> {
+    id: K::Id,
+}
+
+#[test]
+fn generic_derive() {
+    struct Unencodable;
+
+    let signed = Signed::<_, Unencodable> {
+        payload: DeriveTest {
+            field_a: 1,
+            field_b: 2,
+        },
+        parents: vec![None],
+        sig: [1, 2, 3, 4],
+        _marker: Default::default(),
+    };
+    let mut w = Writer::new();
+    signed.encode(&mut w);
+    let encoded = w.output();
+    let decoded = deserialize::<Signed<DeriveTest, Unencodable>>(&encoded).unwrap();
+    assert_eq!(
+        (decoded.payload, decoded.parents),
+        (signed.payload, signed.parents)
+    );
+
+    assert_roundtrip(&SignedWrapper(Signed {
+        payload: vec![u64::MAX],
+        parents: vec![Some(vec![]), None],
+        sig: [0; 4],
+        _marker: Default::default(),
+    }));
+
+    let schema = Signed::<u32, ()>::schema();
+    assert_eq!(
+        schema.to_string().parse::<dynamic::Schema>().unwrap(),
+        schema
+    );
+    assert_eq!(
+        schema.to_string().lines().next(),
+        Some(
+            "struct Signed { payload: u32, parents: vec<option<u32>>, sig: [u8; 4], _marker: (), }"
+        )
+    );
+
+    // each instantiation gets its own definition
+    let schema = Signatures::schema();
+    assert_eq!(
+        schema.to_string(),
+        "struct Signatures { _narrow: Signed_u32, _wide: Signed_u64, }\n\
+         struct Signed_u32 { payload: u32, parents: vec<option<u32>>, sig: [u8; 4], _marker: (), }\n\
+         struct Signed_u64 { payload: u64, parents: vec<option<u64>>, sig: [u8; 4], _marker: (), }\n\
+         Signatures"
+    );
+
+    let r = Ref::<EventKind> { id: [5; 4] };
+    assert_eq!(assert_roundtrip(&r), assert_roundtrip(&[5_u8; 4]));
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Encodable, Decodable, CserSchema)]
+#[cser(transparent, from)]
+struct Byte(u8);
+
+#[derive(Clone, Debug, PartialEq, Encodable, Decodable, CserSchema)]
+#[cser(transparent, from)]
+struct Parents {
+    ids: Vec<[u8; 4]>,
+}
+
+#[derive(Debug, PartialEq, Encodable)]
+#[cser(transparent, from)]
+struct Opaque<T>(T);
+
+#[derive(Encodable)]
+#[cser(transparent, from)]
+struct Borrowed<'a, T>(&'a T);
+
+#[derive(Clone, Debug, PartialEq, EncodableWrapper, DecodableWrapper)]
+struct NamedWrapper {
+    inner: u8,
+}
+
+#[test]
+fn transparent_derive() {
+    assert_eq!(assert_roundtrip(&Byte(7)), assert_roundtrip(&7_u8));
+    // sequences of transparent bytes are byte strings, wrappers keep the item layout
+    assert_eq!(
+        assert_roundtrip(&vec![Byte(1), Byte(2)]),
+        assert_roundtrip(&vec![1_u8, 2])
+    );
+    assert_eq!(
+        assert_roundtrip(&vec![NamedWrapper { inner: 1 }, NamedWrapper { inner: 2 }]),
+        hex!("0201020081")
+    );
+
+    let parents = Parents {
+        ids: vec![[1; 4], [2; 4]],
+    };
+    assert_eq!(assert_roundtrip(&parents), assert_roundtrip(&parents.ids));
+    assert_eq!(Parents::schema(), Vec::<[u8; 4]>::schema());
+    assert_eq!(Byte::schema(), dynamic::Schema::new(dynamic::Type::U8));
+
+    assert_eq!(Byte::from(7), Byte(7));
+    assert_eq!(u8::from(Byte(7)), 7);
+    assert_eq!(Parents::from(parents.ids.clone()), parents);
+    assert_eq!(Vec::from(parents.clone()), parents.ids);
+    assert_eq!(Opaque::from(vec![1_u64]), Opaque(vec![1]));
+    let (mut owned, mut borrowed) = (Writer::new(), Writer::new());
+    Opaque(vec![1_u64]).encode(&mut owned);
+    Borrowed::from(&vec![1_u64]).encode(&mut borrowed);
+    assert_eq!(owned, borrowed);
+}
+
+const MAX_PARENTS: usize = 10;
+
+#[derive(Clone, Debug, Default, PartialEq, Encodable, Decodable)]
+struct BoundedTest {
+    #[cser(max_len = MAX_PARENTS)]
+    parents: Vec<[u8; 32]>,
+    #[cser(max_len = 32)]
+    extra: bytes::Bytes,
+    name: String,
+}
+
+#[test]
+fn bounded_derive() {
+    let v = BoundedTest {
+        parents: vec![[1; 32]; MAX_PARENTS],
+        extra: vec![2; 32].into(),
+        name: "x".repeat(64),
+    };
+    assert_roundtrip(&v);
+
+    let mut w = Writer::new();
+    vec![[1_u8; 32]; MAX_PARENTS + 1].encode(&mut w);
+    bytes::Bytes::new().encode(&mut w);
+    String::new().encode(&mut w);
+    let too_many = w.output();
+    assert!(deserialize::<BoundedTest>(&too_many).is_err());
+    assert!(deserialize_cb_with(&too_many, Strictness::Strict, BoundedTest::skip).is_err());
+}
+
+#[test]
+#[should_panic(expected = "`BoundedTest::extra` is longer than 32")]
+fn bounded_derive_too_long() {
+    let too_long = BoundedTest {
+        extra: vec![2; 33].into(),
+        ..Default::default()
+    };
+    too_long.encode(&mut Writer::new());
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Encodable, Decodable)]
+#[cser(bits = 2)]
+enum RecordKind {
+    #[default]
+    Event,
+    Block,
+    Epoch,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Encodable, Decodable)]
+struct PackedHeader {
+    #[cser(bits = 3)]
+    lamport: u8,
+    #[cser(bits = 1)]
+    flag: bool,
+    kind: RecordKind,
+    #[cser(bits = 10)]
+    frame: u16,
+    name: String,
+}
+
+#[derive(Debug, Encodable, Decodable)]
+struct Narrow {
+    #[cser(bits = 10)]
+    v: u8,
+}
+
+/// Bit-packed, but neither `Encodable` nor `Decodable` on its own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Level(u8);
+
+impl BitPacked for Level {
+    fn to_bits(&self) -> u64 {
+        self.0.into()
+    }
+
+    fn from_bits(v: u64) -> Option<Self> {
+        v.try_into().ok().map(Self)
+    }
+}
+
+#[derive(Debug, PartialEq, Encodable, Decodable)]
+struct Leveled<L> {
+    #[cser(bits = 4)]
+    level: L,
+    id: u32,
+}
+
+#[test]
+fn bits_derive() {
+    let v = PackedHeader {
+        lamport: 5,
+        flag: true,
+        kind: RecordKind::Epoch,
+        frame: 1000,
+        name: "x".into(),
+    };
+    let mut w = Writer::new();
+    w.write_bits(3, 5);
+    w.write_bits(1, 1);
+    w.write_bits(2, 2);
+    w.write_bits(10, 1000);
+    "x".to_string().encode(&mut w);
+    assert_eq!(assert_roundtrip(&v), w.output());
+    assert_roundtrip(&PackedHeader::default());
+
+    let mut w = Writer::new();
+    w.write_bits(2, 3);
+    assert!(matches!(
+        deserialize::<RecordKind>(&w.output()),
+        Err(Error::OverFlowError)
+    ));
+
+    let mut w = Writer::new();
+    w.write_bits(10, 300);
+    let too_wide = w.output();
+    assert!(deserialize::<Narrow>(&too_wide).is_err());
+    assert!(deserialize_cb_with(&too_wide, Strictness::Strict, Narrow::skip).is_err());
+
+    // generic `bits` fields only need to be bit-packed
+    assert_roundtrip(&Leveled {
+        level: Level(9),
+        id: 7,
+    });
+}
+
+#[test]
+#[should_panic(expected = "9 does not fit into 3 bits")]
+fn bits_derive_too_wide() {
+    let v = PackedHeader {
+        lamport: 9,
+        ..Default::default()
+    };
+    v.encode(&mut Writer::new());
+}
+
+proptest! {
+    #[test]
+    fn primitives_roundtrip(a: u8, b: bool, c: u16, d: u32, e: u64, f: i64, g: U56) {
+        assert_roundtrip(&a);
+        assert_roundtrip(&b);
+        assert_roundtrip(&c);
+        assert_roundtrip(&d);
+        assert_roundtrip(&e);
+        assert_roundtrip(&f);
+        assert_roundtrip(&g);
+    }
+
+    #[test]
+    fn containers_roundtrip(
+        a: Vec<u8>,
+        b: Vec<Option<u32>>,
+        c: Option<Vec<i64>>,
+        d: [u8; 32],
+        e: String,
+    ) {
+        assert_roundtrip(&a);
+        assert_roundtrip(&b);
+        assert_roundtrip(&c);
+        assert_roundtrip(&d);
+        assert_roundtrip(&e);
+    }
+
+    #[test]
+    fn derive_roundtrip(field_a: u32, field_b: u64) {
+        assert_roundtrip(&DeriveWrapperTest(DeriveTest { field_a, field_b }));
+    }
+}
+
 #[test]
 fn vec_specialization() {
     {
@@ -50,3 +562,91 @@ fn vec_specialization() {
         assert_eq!(deserialize::<Vec<u64>>(&out).unwrap(), numbers);
     }
 }
+
+#[test]
+fn sequence_layouts() {
+    assert_eq!(
+        assert_roundtrip(&vec![0x42_u8, 0x43_u8]),
+        hex!("0242430181")
+    );
+    assert_eq!(
+        assert_roundtrip(&vec![0xAABB_u64, 0xCCDD_u64]),
+        hex!("02BBAADDCC2481")
+    );
+
+    {
+        // borrowed items are laid out like the owned ones
+        let bytes = [0x42_u8, 0x43_u8];
+        let mut writer = Writer::new();
+        bytes.iter().collect::<Vec<_>>().encode(&mut writer);
+        assert_eq!(writer.output(), hex!("0242430181"));
+
+        let numbers = [0xAABB_u64, 0xCCDD_u64];
+        let mut writer = Writer::new();
+        numbers.iter().collect::<Vec<_>>().encode(&mut writer);
+        assert_eq!(writer.output(), hex!("02BBAADDCC2481"));
+    }
+
+    {
+        let bytestring = ArrayVec::<u8, 2>::from([0x42, 0x43]);
+        assert_eq!(assert_roundtrip(&bytestring), hex!("0242430181"));
+        assert!(deserialize::<ArrayVec<u8, 1>>(&hex!("0242430181")).is_err());
+        assert!(deserialize::<Vec<u8>>(&hex!("0342430181")).is_err());
+    }
+}
+
+#[test]
+fn container_conventions() {
+    let bytes = hex!("0242430181");
+    let numbers = hex!("02BBAADDCC2481");
+
+    assert_eq!(
+        assert_roundtrip(&smallvec::SmallVec::<[u8; 1]>::from_slice(&[0x42, 0x43])),
+        bytes
+    );
+    assert_eq!(
+        assert_roundtrip(&heapless::Vec::<u8, 2>::from_slice(&[0x42, 0x43]).unwrap()),
+        bytes
+    );
+    assert_eq!(
+        assert_roundtrip(&tinyvec::ArrayVec::from([0x42_u8, 0x43])),
+        bytes
+    );
+    assert_eq!(
+        assert_roundtrip(&tinyvec::TinyVec::<[u8; 1]>::from(&[0x42, 0x43][..])),
+        bytes
+    );
+
+    assert_eq!(
+        assert_roundtrip(&smallvec::SmallVec::<[u64; 4]>::from_slice(&[
+            0xAABB, 0xCCDD
+        ])),
+        numbers
+    );
+    assert_eq!(
+        assert_roundtrip(&heapless::Vec::<u64, 2>::from_slice(&[0xAABB, 0xCCDD]).unwrap()),
+        numbers
+    );
+    assert_eq!(
+        assert_roundtrip(&tinyvec::TinyVec::<[u64; 1]>::from(&[0xAABB, 0xCCDD][..])),
+        numbers
+    );
+
+    // capacity exceeded
+    assert!(matches!(
+        deserialize::<heapless::Vec<u8, 1>>(&bytes),
+        Err(Error::OverFlowError)
+    ));
+    assert!(matches!(
+        deserialize::<heapless::Vec<u64, 1>>(&numbers),
+        Err(Error::OverFlowError)
+    ));
+    assert!(matches!(
+        deserialize::<tinyvec::ArrayVec<[u8; 1]>>(&bytes),
+        Err(Error::OverFlowError)
+    ));
+    assert!(matches!(
+        deserialize::<tinyvec::ArrayVec<[u64; 1]>>(&numbers),
+        Err(Error::OverFlowError)
+    ));
+}
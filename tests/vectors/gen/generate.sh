@@ -0,0 +1,19 @@
+#!/bin/sh
+# Writes ../go_opera.json with the go-opera release pinned below, updating go.mod and
+# go.sum to match. Needs go and jq.
+set -eu
+
+GO_OPERA=github.com/Fantom-foundation/go-opera
+GO_OPERA_VERSION=${GO_OPERA_VERSION:-v1.1.3-rc.5}
+
+cd "$(dirname "$0")"
+
+go mod edit -require="$GO_OPERA@$GO_OPERA_VERSION"
+# go-opera builds against forks of its dependencies, so take over the `replace`
+# directives of its own go.mod
+gomod=$(go mod download -json "$GO_OPERA@$GO_OPERA_VERSION" | jq -r .GoMod)
+go mod edit -json "$gomod" |
+    jq -r '.Replace[]? | select(.New.Version) | "-replace=\(.Old.Path)=\(.New.Path)@\(.New.Version)"' |
+    xargs -r go mod edit
+go mod tidy
+go run .
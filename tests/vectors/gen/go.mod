@@ -0,0 +1,3 @@
+module github.com/vorot93/cser/tests/vectors/gen
+
+go 1.21
@@ -0,0 +1,373 @@
+// Command gen writes ../go_opera.json using go-opera's utils/cser.
+//
+// Every input of ../hand_derived.json is decoded by the Go implementation, which decides
+// whether it is accepted, what it decodes to and whether it is canonical. Opera events and
+// transactions are encoded by go-opera itself.
+package main
+
+import (
+	"bytes"
+	"encoding/hex"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"math/big"
+	"os"
+	"unicode/utf8"
+
+	"github.com/Fantom-foundation/go-opera/inter"
+	"github.com/Fantom-foundation/go-opera/utils/cser"
+	"github.com/Fantom-foundation/lachesis-base/hash"
+	"github.com/Fantom-foundation/lachesis-base/inter/idx"
+	"github.com/ethereum/go-ethereum/common"
+	"github.com/ethereum/go-ethereum/core/types"
+)
+
+// maxSliceLen bounds byte slices, like the `usize::MAX` of the Rust decoder in practice.
+const maxSliceLen = 1 << 30
+
+type vector struct {
+	Name      string           `json:"name"`
+	Type      string           `json:"type"`
+	Value     *json.RawMessage `json:"value,omitempty"`
+	Hex       string           `json:"hex"`
+	Accept    bool             `json:"accept"`
+	Canonical *bool            `json:"canonical,omitempty"`
+}
+
+// codec reads and writes values of one vector type. Values are kept in their JSON form.
+type codec struct {
+	read  func(r *cser.Reader) interface{}
+	write func(w *cser.Writer, v interface{})
+}
+
+func uint64Codec(read func(r *cser.Reader) uint64, write func(w *cser.Writer, v uint64)) codec {
+	return codec{
+		read:  func(r *cser.Reader) interface{} { return read(r) },
+		write: func(w *cser.Writer, v interface{}) { write(w, v.(uint64)) },
+	}
+}
+
+func fixedCodec(n int) codec {
+	return codec{
+		read: func(r *cser.Reader) interface{} {
+			buf := make([]byte, n)
+			r.FixedBytes(buf)
+			return "0x" + hex.EncodeToString(buf)
+		},
+		write: func(w *cser.Writer, v interface{}) { w.FixedBytes(unhex(v.(string))) },
+	}
+}
+
+// listCodec follows the convention of the Rust crate: a u32 count followed by the items.
+func listCodec(item codec) codec {
+	return codec{
+		read: func(r *cser.Reader) interface{} {
+			n := r.U32()
+			items := []interface{}{}
+			for i := uint32(0); i < n; i++ {
+				items = append(items, item.read(r))
+			}
+			return items
+		},
+		write: func(w *cser.Writer, v interface{}) {
+			items := v.([]interface{})
+			w.U32(uint32(len(items)))
+			for _, v := range items {
+				item.write(w, v)
+			}
+		},
+	}
+}
+
+// optionCodec follows the convention of the Rust crate: a presence bit followed by the value.
+func optionCodec(value codec) codec {
+	return codec{
+		read: func(r *cser.Reader) interface{} {
+			if !r.Bool() {
+				return nil
+			}
+			return value.read(r)
+		},
+		write: func(w *cser.Writer, v interface{}) {
+			w.Bool(v != nil)
+			if v != nil {
+				value.write(w, v)
+			}
+		},
+	}
+}
+
+var (
+	u8Codec = codec{
+		read:  func(r *cser.Reader) interface{} { return uint64(r.U8()) },
+		write: func(w *cser.Writer, v interface{}) { w.U8(uint8(v.(uint64))) },
+	}
+	u32Codec = uint64Codec(
+		func(r *cser.Reader) uint64 { return uint64(r.U32()) },
+		func(w *cser.Writer, v uint64) { w.U32(uint32(v)) },
+	)
+	u64Codec = uint64Codec(
+		func(r *cser.Reader) uint64 { return r.U64() },
+		func(w *cser.Writer, v uint64) { w.U64(v) },
+	)
+	boolCodec = codec{
+		read:  func(r *cser.Reader) interface{} { return r.Bool() },
+		write: func(w *cser.Writer, v interface{}) { w.Bool(v.(bool)) },
+	}
+)
+
+var codecs = map[string]codec{
+	"bool": boolCodec,
+	"u8":   u8Codec,
+	"u16": uint64Codec(
+		func(r *cser.Reader) uint64 { return uint64(r.U16()) },
+		func(w *cser.Writer, v uint64) { w.U16(uint16(v)) },
+	),
+	"u32": u32Codec,
+	"u64": u64Codec,
+	"i64": {
+		read:  func(r *cser.Reader) interface{} { return r.I64() },
+		write: func(w *cser.Writer, v interface{}) { w.I64(v.(int64)) },
+	},
+	"u56": uint64Codec(
+		func(r *cser.Reader) uint64 { return r.U56() },
+		func(w *cser.Writer, v uint64) { w.U56(v) },
+	),
+	"u256": {
+		read: func(r *cser.Reader) interface{} {
+			v := r.BigInt()
+			if v.BitLen() > 256 {
+				panic(cser.ErrTooLargeAlloc)
+			}
+			return v.String()
+		},
+		write: func(w *cser.Writer, v interface{}) {
+			n, _ := new(big.Int).SetString(v.(string), 10)
+			w.BigInt(n)
+		},
+	},
+	"[u8; 0]": fixedCodec(0),
+	"[u8; 4]": fixedCodec(4),
+	"address": fixedCodec(20),
+	"h256":    fixedCodec(32),
+	"bytes": {
+		read: func(r *cser.Reader) interface{} {
+			return "0x" + hex.EncodeToString(r.SliceBytes(maxSliceLen))
+		},
+		write: func(w *cser.Writer, v interface{}) { w.SliceBytes(unhex(v.(string))) },
+	},
+	// byte vectors are written as byte slices
+	"vec<u8>": {
+		read: func(r *cser.Reader) interface{} {
+			items := []interface{}{}
+			for _, b := range r.SliceBytes(maxSliceLen) {
+				items = append(items, uint64(b))
+			}
+			return items
+		},
+		write: func(w *cser.Writer, v interface{}) {
+			buf := []byte{}
+			for _, b := range v.([]interface{}) {
+				buf = append(buf, uint8(b.(uint64)))
+			}
+			w.SliceBytes(buf)
+		},
+	},
+	"string": {
+		read: func(r *cser.Reader) interface{} {
+			buf := r.SliceBytes(maxSliceLen)
+			if !utf8.Valid(buf) {
+				panic(cser.ErrMalformedEncoding)
+			}
+			return string(buf)
+		},
+		write: func(w *cser.Writer, v interface{}) { w.SliceBytes([]byte(v.(string))) },
+	},
+	"vec<u64>":         listCodec(u64Codec),
+	"vec<bool>":        listCodec(boolCodec),
+	"option<u64>":      optionCodec(u64Codec),
+	"vec<option<u32>>": listCodec(optionCodec(u32Codec)),
+}
+
+func unhex(s string) []byte {
+	if len(s) >= 2 && s[:2] == "0x" {
+		s = s[2:]
+	}
+	b, err := hex.DecodeString(s)
+	if err != nil {
+		panic(err)
+	}
+	return b
+}
+
+func raw(v interface{}) *json.RawMessage {
+	b, err := json.Marshal(v)
+	if err != nil {
+		panic(err)
+	}
+	m := json.RawMessage(b)
+	return &m
+}
+
+// decode runs a hand-derived input through go-opera.
+func decode(name, ty, input string) vector {
+	c, ok := codecs[ty]
+	if !ok {
+		panic(fmt.Sprintf("%s: unknown type %s", name, ty))
+	}
+	buf := unhex(input)
+	out := vector{Name: name, Type: ty, Hex: input}
+
+	var v interface{}
+	err := cser.UnmarshalBinaryAdapter(buf, func(r *cser.Reader) error {
+		v = c.read(r)
+		return nil
+	})
+	if err != nil {
+		return out
+	}
+	out.Accept = true
+	out.Value = raw(v)
+
+	encoded, err := cser.MarshalBinaryAdapter(func(w *cser.Writer) error {
+		c.write(w, v)
+		return nil
+	})
+	if err != nil {
+		panic(err)
+	}
+	if !bytes.Equal(encoded, buf) {
+		canonical := false
+		out.Canonical = &canonical
+	}
+	return out
+}
+
+func parents(epoch idx.Epoch, lamports ...idx.Lamport) hash.Events {
+	events := hash.Events{}
+	for i, lamport := range lamports {
+		var h hash.Event
+		copy(h[:4], epoch.Bytes())
+		copy(h[4:8], lamport.Bytes())
+		for j := 8; j < len(h); j++ {
+			h[j] = byte(0xAA + i)
+		}
+		events = append(events, h)
+	}
+	return events
+}
+
+func event(version uint8, epoch idx.Epoch) *inter.MutableEventPayload {
+	e := &inter.MutableEventPayload{}
+	e.SetVersion(version)
+	if version > 0 {
+		e.SetNetForkID(3)
+	}
+	e.SetEpoch(epoch)
+	e.SetSeq(7)
+	e.SetFrame(5)
+	e.SetCreator(42)
+	e.SetLamport(100)
+	e.SetCreationTime(1_700_000_000_000_000_000)
+	e.SetMedianTime(1_699_999_999_000_000_000)
+	e.SetGasPowerUsed(28_000)
+	e.SetGasPowerLeft(inter.GasPowerLeft{Gas: [2]uint64{1_000_000, 5_000_000}})
+	e.SetParents(parents(epoch, 99, 1))
+	prev := hash.BytesToHash(bytes.Repeat([]byte{0xCC}, 32))
+	e.SetPrevEpochHash(&prev)
+	e.SetExtra([]byte("extra"))
+	return e
+}
+
+// txs mirror the transactions of the Rust tests of `opera::Transaction`.
+func txs() types.Transactions {
+	to := common.BytesToAddress(bytes.Repeat([]byte{0x55}, 20))
+	data := []byte{0xA9, 0x05, 0x9C, 0xBB}
+	value := new(big.Int).Exp(big.NewInt(10), big.NewInt(18), nil)
+	r := new(big.Int).Sub(new(big.Int).Lsh(big.NewInt(1), 256), big.NewInt(1))
+	accessList := types.AccessList{{
+		Address:     common.BytesToAddress(bytes.Repeat([]byte{0x66}, 20)),
+		StorageKeys: []common.Hash{common.BytesToHash(bytes.Repeat([]byte{0x77}, 32))},
+	}}
+
+	return types.Transactions{
+		types.NewTx(&types.LegacyTx{
+			Nonce: 1, GasPrice: big.NewInt(100_000_000_000), Gas: 21_000, To: &to,
+			Value: value, Data: data, V: big.NewInt(0x1F9), R: r, S: big.NewInt(1),
+		}),
+		types.NewTx(&types.AccessListTx{
+			ChainID: big.NewInt(250), Nonce: 1, GasPrice: big.NewInt(100_000_000_000),
+			Gas: 21_000, To: &to, Value: value, Data: data, AccessList: accessList,
+			V: big.NewInt(1), R: r, S: big.NewInt(1),
+		}),
+		types.NewTx(&types.DynamicFeeTx{
+			ChainID: big.NewInt(250), Nonce: 1, GasTipCap: big.NewInt(2_000_000_000),
+			GasFeeCap: big.NewInt(100_000_000_000), Gas: 21_000, To: &to, Value: value,
+			Data: data, AccessList: accessList, V: big.NewInt(1), R: r, S: big.NewInt(1),
+		}),
+	}
+}
+
+func encode(name, ty string, marshal func(w *cser.Writer) error) vector {
+	buf, err := cser.MarshalBinaryAdapter(marshal)
+	if err != nil {
+		panic(fmt.Sprintf("%s: %v", name, err))
+	}
+	return vector{Name: name, Type: ty, Hex: hex.EncodeToString(buf), Accept: true}
+}
+
+func main() {
+	var inputs []vector
+	data, err := os.ReadFile("../hand_derived.json")
+	if err != nil {
+		panic(err)
+	}
+	if err := json.Unmarshal(data, &inputs); err != nil {
+		panic(err)
+	}
+
+	vectors := []vector{}
+	for _, in := range inputs {
+		vectors = append(vectors, decode(in.Name, in.Type, in.Hex))
+	}
+
+	for i, tx := range txs() {
+		tx := tx
+		vectors = append(vectors, encode(
+			fmt.Sprintf("tx type %d", i), "tx",
+			func(w *cser.Writer) error { return inter.TransactionMarshalCSER(w, tx) },
+		))
+	}
+
+	for _, version := range []uint8{0, 1} {
+		e := event(version, 0x1234)
+		e.SetPayloadHash(hash.BytesToHash(bytes.Repeat([]byte{0xDD}, 32)))
+		header := e.Build()
+		vectors = append(vectors, encode(
+			fmt.Sprintf("event v%d", version), "event", header.Event.MarshalCSER,
+		))
+
+		e.SetSig(inter.BytesToSignature(bytes.Repeat([]byte{0x11}, 64)))
+		if version > 0 {
+			e.SetTxs(txs())
+		}
+		payload := e.Build()
+		vectors = append(vectors, encode(
+			fmt.Sprintf("event payload v%d", version), "event_payload", payload.MarshalCSER,
+		))
+	}
+
+	// go-opera refuses to encode version 0 events which look versioned
+	if _, err := cser.MarshalBinaryAdapter(event(0, 1).Build().Event.MarshalCSER); err == nil {
+		panic(errors.New("version 0 event with epoch 1 encoded"))
+	}
+
+	out, err := json.MarshalIndent(vectors, "", "  ")
+	if err != nil {
+		panic(err)
+	}
+	if err := os.WriteFile("../go_opera.json", append(out, '\n'), 0o644); err != nil {
+		panic(err)
+	}
+}